@@ -1,30 +1,87 @@
 use async_graphql::extensions::Tracing;
-use async_graphql::{Context, EmptySubscription, ErrorExtensions, InputObject, Object, Schema};
+use async_graphql::{
+    Context, EmptySubscription, ErrorExtensions, GuardExt, InputObject, Object, Schema, Value,
+};
 use chrono::{DateTime, Utc};
 use docstore_domain::model;
-use docstore_domain::model::document::{Document, Genre};
+use docstore_domain::model::api_key::{ApiKey, CreatedApiKey};
+use docstore_domain::model::authorization::{Permission, Principal};
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::document::{BulkAddOutcome, Document, Genre};
 use docstore_domain::model::error::Error as ModelError;
+use docstore_domain::model::validation::FieldError;
+use docstore_domain::ports::primary::api_key::ApiKeyManagement;
 use docstore_domain::ports::primary::storage::DocumentStorage;
+use docstore_domain::ports::secondary::authorization::AuthorizationPolicy;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
+use std::convert::TryFrom;
 use std::str::FromStr;
+use std::sync::Arc;
 use tracing::instrument;
 use uuid::Uuid;
 
+use crate::auth::{Authenticated, PermissionGuard};
+use crate::limits::{LimitsConfig, QueryCost};
+use crate::metrics::{ResolverObserver, ResolverTiming};
+use crate::persisted::{AllowList, Error as PersistedError};
+use crate::GraphqlConfig;
+
 #[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
 pub enum Error {
     #[snafu(display("Model Error: {} - {}", msg, source))]
     Model { msg: String, source: ModelError },
 
     #[snafu(display("Request Error: {} - {}", msg, source))]
     Reqwest { msg: String, source: reqwest::Error },
+
+    #[snafu(display("Limit Exceeded: {} is above the maximum of {}", limit, max))]
+    LimitExceeded { limit: u32, max: u32 },
+
+    #[snafu(display("Schema Error: {}", source))]
+    Schema { source: PersistedError },
+
+    #[snafu(display("Authentication required"))]
+    Unauthenticated,
+
+    #[snafu(display("Invalid Request: {}", msg))]
+    InvalidRequest { msg: String },
 }
 
 impl ErrorExtensions for Error {
     fn extend(&self) -> async_graphql::Error {
         self.extend_with(|err, e| match err {
-            Error::Model { msg, .. } => e.set("reason", msg.to_string()),
+            Error::Model { msg, source } => {
+                e.set("reason", msg.to_string());
+                match source {
+                    ModelError::PermissionDenied { .. } => e.set("code", "FORBIDDEN"),
+                    ModelError::DeadlineExceeded => e.set("code", "DEADLINE_EXCEEDED"),
+                    ModelError::NotFound => e.set("code", "NOT_FOUND"),
+                    ModelError::Validation { errors } => {
+                        e.set("code", "VALIDATION_FAILED");
+                        if let Ok(fields) = serde_json::to_value(errors).and_then(Value::from_json)
+                        {
+                            e.set("fields", fields);
+                        }
+                    }
+                    _ => {}
+                }
+            }
             Error::Reqwest { msg, .. } => e.set("reason", msg.to_string()),
+            Error::LimitExceeded { .. } => {
+                e.set("reason", err.to_string());
+                e.set("code", "LIMIT_EXCEEDED");
+            }
+            Error::Schema { .. } => e.set("reason", err.to_string()),
+            Error::Unauthenticated => {
+                e.set("reason", err.to_string());
+                e.set("code", "UNAUTHENTICATED");
+            }
+            Error::InvalidRequest { msg } => {
+                e.set("reason", msg.to_string());
+                e.set("code", "BAD_USER_INPUT");
+            }
         })
     }
 }
@@ -43,7 +100,36 @@ pub struct ListDocumentsRequest {
 impl From<ListDocumentsRequest> for model::document::ListDocumentsRequest {
     fn from(request: ListDocumentsRequest) -> Self {
         let ListDocumentsRequest { offset, limit } = request;
-        model::document::ListDocumentsRequest { offset, limit }
+        model::document::ListDocumentsRequest {
+            offset,
+            limit,
+            genre: None,
+            tag: None,
+        }
+    }
+}
+
+// A GraphQL Input Object to encapsulate the request parameters to list the documents
+// created by an author.
+#[derive(Serialize, Deserialize, Debug, InputObject)]
+pub struct ListDocumentsByAuthorRequest {
+    pub author: String,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl From<ListDocumentsByAuthorRequest> for model::document::ListDocumentsByAuthorRequest {
+    fn from(request: ListDocumentsByAuthorRequest) -> Self {
+        let ListDocumentsByAuthorRequest {
+            author,
+            offset,
+            limit,
+        } = request;
+        model::document::ListDocumentsByAuthorRequest {
+            author,
+            offset,
+            limit,
+        }
     }
 }
 
@@ -72,6 +158,7 @@ pub struct DocumentResponse {
     pub genre: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub author: Option<String>,
 }
 
 #[Object]
@@ -111,6 +198,11 @@ impl DocumentResponse {
     async fn updated_at(&self) -> &DateTime<Utc> {
         &self.updated_at
     }
+
+    /// Subject of the principal who created the document.
+    async fn author(&self) -> &Option<String> {
+        &self.author
+    }
 }
 
 impl From<Document> for DocumentResponse {
@@ -125,6 +217,8 @@ impl From<Document> for DocumentResponse {
             genre,
             created_at,
             updated_at,
+            created_by,
+            ..
         } = document;
 
         DocumentResponse {
@@ -137,6 +231,7 @@ impl From<Document> for DocumentResponse {
             genre: genre.as_str().to_string(),
             created_at,
             updated_at,
+            author: created_by,
         }
     }
 }
@@ -189,18 +284,237 @@ impl From<Document> for GetDocumentResponse {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldErrorResponse {
+    pub field: String,
+    pub message: String,
+}
+
+#[Object]
+impl FieldErrorResponse {
+    async fn field(&self) -> &String {
+        &self.field
+    }
+
+    async fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+impl From<FieldError> for FieldErrorResponse {
+    fn from(error: FieldError) -> Self {
+        let FieldError { field, message } = error;
+        FieldErrorResponse { field, message }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddDocumentResult {
+    /// One of 'created', 'conflict', 'invalid', 'aborted'.
+    pub status: String,
+    pub id: Option<Uuid>,
+    pub document: Option<DocumentResponse>,
+    pub errors: Vec<FieldErrorResponse>,
+}
+
+#[Object]
+impl AddDocumentResult {
+    /// One of 'created', 'conflict', 'invalid', 'aborted'.
+    async fn status(&self) -> &String {
+        &self.status
+    }
+
+    /// The id of the document, when it is known.
+    async fn id(&self) -> &Option<Uuid> {
+        &self.id
+    }
+
+    /// The added document, when it was created.
+    async fn document(&self) -> &Option<DocumentResponse> {
+        &self.document
+    }
+
+    /// The invalid fields, when the document is invalid.
+    async fn errors(&self) -> &Vec<FieldErrorResponse> {
+        &self.errors
+    }
+}
+
+impl From<BulkAddOutcome> for AddDocumentResult {
+    fn from(outcome: BulkAddOutcome) -> Self {
+        let (status, id, document, errors) = match outcome {
+            BulkAddOutcome::Created { document } => (
+                "created",
+                Some(document.id),
+                Some(DocumentResponse::from(document)),
+                Vec::new(),
+            ),
+            BulkAddOutcome::Conflict { id } => ("conflict", Some(id), None, Vec::new()),
+            BulkAddOutcome::Invalid { errors } => (
+                "invalid",
+                None,
+                None,
+                errors.into_iter().map(FieldErrorResponse::from).collect(),
+            ),
+            BulkAddOutcome::Aborted => ("aborted", None, None, Vec::new()),
+        };
+        AddDocumentResult {
+            status: status.to_string(),
+            id,
+            document,
+            errors,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddDocumentsResponse {
+    pub results: Vec<AddDocumentResult>,
+    pub created: usize,
+}
+
+#[Object]
+impl AddDocumentsResponse {
+    /// The result of each document, in the order of the request.
+    async fn results(&self) -> &Vec<AddDocumentResult> {
+        &self.results
+    }
+
+    /// Number of documents created.
+    async fn created(&self) -> &usize {
+        &self.created
+    }
+}
+
+impl From<Vec<BulkAddOutcome>> for AddDocumentsResponse {
+    fn from(outcomes: Vec<BulkAddOutcome>) -> Self {
+        let results = outcomes
+            .into_iter()
+            .map(AddDocumentResult::from)
+            .collect::<Vec<_>>();
+        let created = results
+            .iter()
+            .filter(|result| result.document.is_some())
+            .count();
+        AddDocumentsResponse { results, created }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[Object]
+impl ApiKeyResponse {
+    async fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    async fn name(&self) -> &String {
+        &self.name
+    }
+
+    async fn scopes(&self) -> &Vec<String> {
+        &self.scopes
+    }
+
+    async fn created_at(&self) -> &DateTime<Utc> {
+        &self.created_at
+    }
+
+    async fn expires_at(&self) -> &Option<DateTime<Utc>> {
+        &self.expires_at
+    }
+
+    async fn revoked_at(&self) -> &Option<DateTime<Utc>> {
+        &self.revoked_at
+    }
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        let ApiKey {
+            id,
+            name,
+            scopes,
+            created_at,
+            expires_at,
+            revoked_at,
+        } = key;
+
+        ApiKeyResponse {
+            id,
+            name,
+            scopes: scopes
+                .iter()
+                .map(|scope| scope.as_str().to_string())
+                .collect(),
+            created_at,
+            expires_at,
+            revoked_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateApiKeyResponse {
+    pub key: ApiKeyResponse,
+    pub secret: String,
+}
+
+#[Object]
+impl CreateApiKeyResponse {
+    async fn key(&self) -> &ApiKeyResponse {
+        &self.key
+    }
+
+    /// The secret to use in the 'X-Api-Key' header. It cannot be retrieved later.
+    async fn secret(&self) -> &String {
+        &self.secret
+    }
+}
+
+impl From<CreatedApiKey> for CreateApiKeyResponse {
+    fn from(created: CreatedApiKey) -> Self {
+        let CreatedApiKey { key, secret } = created;
+        CreateApiKeyResponse {
+            key: ApiKeyResponse::from(key),
+            secret,
+        }
+    }
+}
+
 pub struct Query;
 
 #[Object]
 impl Query {
+    #[graphql(guard = "PermissionGuard::new(Permission::Read)")]
     async fn list_documents(
         &self,
         context: &Context<'_>,
         request: ListDocumentsRequest,
     ) -> async_graphql::Result<ListDocumentsResponse> {
+        let limits = context.data::<LimitsConfig>()?;
+        if request.limit > limits.max_list_limit {
+            return Err(Error::LimitExceeded {
+                limit: request.limit,
+                max: limits.max_list_limit,
+            }
+            .extend());
+        }
         let service = get_service_from_context(context)?;
         let documents = service
-            .list_documents(&model::document::ListDocumentsRequest::from(request))
+            .list_documents(
+                get_request_context(context),
+                &model::document::ListDocumentsRequest::from(request),
+            )
             .await
             .context(Model {
                 msg: "Error Listing Documents",
@@ -209,6 +523,35 @@ impl Query {
         Ok(ListDocumentsResponse::from(documents))
     }
 
+    #[graphql(guard = "PermissionGuard::new(Permission::Read)")]
+    async fn documents_by_author(
+        &self,
+        context: &Context<'_>,
+        request: ListDocumentsByAuthorRequest,
+    ) -> async_graphql::Result<ListDocumentsResponse> {
+        let limits = context.data::<LimitsConfig>()?;
+        if request.limit > limits.max_list_limit {
+            return Err(Error::LimitExceeded {
+                limit: request.limit,
+                max: limits.max_list_limit,
+            }
+            .extend());
+        }
+        let service = get_service_from_context(context)?;
+        let documents = service
+            .list_documents_by_author(
+                get_request_context(context),
+                &model::document::ListDocumentsByAuthorRequest::from(request),
+            )
+            .await
+            .context(Model {
+                msg: "Error Listing Documents By Author",
+            })
+            .map_err(|e| e.extend())?;
+        Ok(ListDocumentsResponse::from(documents))
+    }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::Read)")]
     async fn get_document(
         &self,
         context: &Context<'_>,
@@ -216,7 +559,10 @@ impl Query {
     ) -> async_graphql::Result<GetDocumentResponse> {
         let service = get_service_from_context(context)?;
         let document = service
-            .get_document(&model::document::GetDocumentRequest::from(request))
+            .get_document(
+                get_request_context(context),
+                &model::document::GetDocumentRequest::from(request),
+            )
             .await
             .context(Model {
                 msg: "Error Getting Document",
@@ -224,13 +570,30 @@ impl Query {
             .map_err(|e| e.extend())?;
         Ok(GetDocumentResponse::from(document))
     }
+
+    #[graphql(guard = "PermissionGuard::new(Permission::Admin)")]
+    async fn list_api_keys(
+        &self,
+        context: &Context<'_>,
+    ) -> async_graphql::Result<Vec<ApiKeyResponse>> {
+        let api_keys = get_api_keys_from_context(context)?;
+        let keys = api_keys
+            .list_api_keys(get_request_context(context))
+            .await
+            .context(Model {
+                msg: "Error Listing API Keys",
+            })
+            .map_err(|e| e.extend())?;
+        Ok(keys.into_iter().map(ApiKeyResponse::from).collect())
+    }
 }
 
 pub struct Mutation;
 
 #[derive(Serialize, Deserialize, Debug, InputObject)]
 pub struct AddDocumentRequest {
-    pub id: Uuid,
+    /// The id of the new document, generated by the server if it is not given.
+    pub id: Option<Uuid>,
     pub title: String,
     pub outline: String,
     pub content: String,
@@ -262,9 +625,77 @@ impl From<AddDocumentRequest> for model::document::AddDocumentRequest {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, InputObject)]
+pub struct AddDocumentsRequest {
+    pub documents: Vec<AddDocumentRequest>,
+    /// When set, either every document is added, or none is.
+    #[graphql(default)]
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+impl From<AddDocumentsRequest> for model::document::BulkAddDocumentsRequest {
+    fn from(request: AddDocumentsRequest) -> Self {
+        let AddDocumentsRequest { documents, atomic } = request;
+        model::document::BulkAddDocumentsRequest {
+            documents: documents
+                .into_iter()
+                .map(model::document::AddDocumentRequest::from)
+                .collect(),
+            atomic,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, InputObject)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Permissions granted to the key: 'read', 'write', 'admin'.
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<CreateApiKeyRequest> for model::api_key::CreateApiKeyRequest {
+    type Error = Error;
+
+    fn try_from(request: CreateApiKeyRequest) -> Result<Self, Self::Error> {
+        let CreateApiKeyRequest {
+            name,
+            scopes,
+            expires_at,
+        } = request;
+        let scopes = scopes
+            .iter()
+            .map(|scope| {
+                Permission::from_str(scope).map_err(|_| Error::InvalidRequest {
+                    msg: format!("Unknown scope '{}'", scope),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(model::api_key::CreateApiKeyRequest {
+            name,
+            scopes,
+            expires_at,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, InputObject)]
+pub struct RevokeApiKeyRequest {
+    pub id: Uuid,
+}
+
+impl From<RevokeApiKeyRequest> for model::api_key::RevokeApiKeyRequest {
+    fn from(request: RevokeApiKeyRequest) -> Self {
+        let RevokeApiKeyRequest { id } = request;
+        model::api_key::RevokeApiKeyRequest { id }
+    }
+}
+
 #[Object]
 impl Mutation {
     #[instrument(skip(self, context))]
+    #[graphql(guard = "Authenticated.and(PermissionGuard::new(Permission::Write))")]
     async fn add_document(
         &self,
         context: &Context<'_>,
@@ -272,7 +703,10 @@ impl Mutation {
     ) -> async_graphql::Result<DocumentResponse> {
         let service = get_service_from_context(context)?;
         let document = service
-            .add_document(&model::document::AddDocumentRequest::from(request))
+            .add_document(
+                get_request_context(context),
+                &model::document::AddDocumentRequest::from(request),
+            )
             .await
             .context(Model {
                 msg: "Error Adding Document",
@@ -281,15 +715,109 @@ impl Mutation {
 
         Ok(DocumentResponse::from(document))
     }
+
+    #[instrument(skip(self, context, request), fields(count = request.documents.len()))]
+    #[graphql(guard = "Authenticated.and(PermissionGuard::new(Permission::Write))")]
+    async fn add_documents(
+        &self,
+        context: &Context<'_>,
+        request: AddDocumentsRequest,
+    ) -> async_graphql::Result<AddDocumentsResponse> {
+        let service = get_service_from_context(context)?;
+        let outcomes = service
+            .bulk_add_documents(
+                get_request_context(context),
+                &model::document::BulkAddDocumentsRequest::from(request),
+            )
+            .await
+            .context(Model {
+                msg: "Error Adding Documents",
+            })
+            .map_err(|e| e.extend())?;
+
+        Ok(AddDocumentsResponse::from(outcomes))
+    }
+
+    #[instrument(skip(self, context))]
+    #[graphql(guard = "PermissionGuard::new(Permission::Admin)")]
+    async fn create_api_key(
+        &self,
+        context: &Context<'_>,
+        request: CreateApiKeyRequest,
+    ) -> async_graphql::Result<CreateApiKeyResponse> {
+        let request =
+            model::api_key::CreateApiKeyRequest::try_from(request).map_err(|e| e.extend())?;
+        let api_keys = get_api_keys_from_context(context)?;
+        let created = api_keys
+            .create_api_key(get_request_context(context), &request)
+            .await
+            .context(Model {
+                msg: "Error Creating API Key",
+            })
+            .map_err(|e| e.extend())?;
+
+        Ok(CreateApiKeyResponse::from(created))
+    }
+
+    #[instrument(skip(self, context))]
+    #[graphql(guard = "PermissionGuard::new(Permission::Admin)")]
+    async fn revoke_api_key(
+        &self,
+        context: &Context<'_>,
+        request: RevokeApiKeyRequest,
+    ) -> async_graphql::Result<ApiKeyResponse> {
+        let api_keys = get_api_keys_from_context(context)?;
+        let key = api_keys
+            .revoke_api_key(
+                get_request_context(context),
+                &model::api_key::RevokeApiKeyRequest::from(request),
+            )
+            .await
+            .context(Model {
+                msg: "Error Revoking API Key",
+            })
+            .map_err(|e| e.extend())?;
+
+        Ok(ApiKeyResponse::from(key))
+    }
 }
 
 pub type DocStoreSchema = Schema<Query, Mutation, EmptySubscription>;
 
-pub fn schema(service: Box<dyn DocumentStorage + Send + Sync>) -> DocStoreSchema {
-    Schema::build(Query, Mutation, EmptySubscription)
-        .extension(Tracing)
+pub fn schema(
+    service: Box<dyn DocumentStorage + Send + Sync>,
+    api_keys: Box<dyn ApiKeyManagement + Send + Sync>,
+    policy: Arc<dyn AuthorizationPolicy + Send + Sync>,
+    observer: Option<Arc<dyn ResolverObserver>>,
+    config: &GraphqlConfig,
+) -> Result<DocStoreSchema, Error> {
+    let mut builder = Schema::build(Query, Mutation, EmptySubscription).extension(Tracing);
+    if let Some(observer) = observer {
+        builder = builder.extension(ResolverTiming::new(observer));
+    }
+
+    // The allow-list must come after the persisted queries, so that it checks
+    // the query retrieved from its hash.
+    if let Some(persisted_queries) = config.persisted_queries.extension() {
+        builder = builder.extension(persisted_queries);
+    }
+    if config.allow_list.enabled {
+        let allow_list = AllowList::from_dir(&config.allow_list.path).context(Schema)?;
+        builder = builder.extension(allow_list);
+    }
+
+    if config.disable_introspection {
+        builder = builder.disable_introspection();
+    }
+
+    Ok(builder
+        .extension(QueryCost::new(config.limits.clone()))
+        .limit_depth(config.limits.max_depth)
+        .data(config.limits.clone())
+        .data(policy)
         .data(service)
-        .finish()
+        .data(api_keys)
+        .finish())
 }
 
 #[allow(clippy::borrowed_box)]
@@ -300,3 +828,30 @@ where
 {
     context.data::<Box<dyn DocumentStorage + Send + Sync>>()
 }
+
+#[allow(clippy::borrowed_box)]
+pub fn get_api_keys_from_context<'ctx>(
+    context: &'ctx Context,
+) -> Result<&'ctx Box<dyn ApiKeyManagement + Send + Sync>, async_graphql::Error> {
+    context.data::<Box<dyn ApiKeyManagement + Send + Sync>>()
+}
+
+// The context used when there is none in the request data.
+static DEFAULT_REQUEST_CONTEXT: RequestContext = RequestContext {
+    request_id: Uuid::nil(),
+    principal: Principal {
+        subject: None,
+        roles: Vec::new(),
+        scopes: None,
+    },
+    deadline: None,
+    locale: None,
+};
+
+// Returns the request context found in the request data, or a context with an
+// anonymous principal.
+pub fn get_request_context<'ctx>(context: &'ctx Context) -> &'ctx RequestContext {
+    context
+        .data_opt::<RequestContext>()
+        .unwrap_or(&DEFAULT_REQUEST_CONTEXT)
+}
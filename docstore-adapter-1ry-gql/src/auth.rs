@@ -0,0 +1,57 @@
+use async_graphql::{Context, ErrorExtensions, Guard};
+use docstore_domain::model::authorization::Permission;
+use docstore_domain::ports::secondary::authorization::AuthorizationPolicy;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::sync::Arc;
+
+use crate::api::{get_request_context, Error, Model};
+
+/// The claims of a validated bearer token.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Claims {
+    /// Subject of the token, ie the caller's identity.
+    pub sub: String,
+    /// Expiration time (as a UTC timestamp).
+    pub exp: usize,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+// A guard rejecting anonymous callers.
+pub struct Authenticated;
+
+#[async_trait::async_trait]
+impl Guard for Authenticated {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if get_request_context(ctx).principal.is_anonymous() {
+            Err(Error::Unauthenticated.extend())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// A guard rejecting callers which are not granted the permission by the authorization policy.
+pub struct PermissionGuard {
+    permission: Permission,
+}
+
+impl PermissionGuard {
+    pub fn new(permission: Permission) -> Self {
+        PermissionGuard { permission }
+    }
+}
+
+#[async_trait::async_trait]
+impl Guard for PermissionGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let policy = ctx.data::<Arc<dyn AuthorizationPolicy + Send + Sync>>()?;
+        policy
+            .authorize(&get_request_context(ctx).principal, self.permission)
+            .context(Model {
+                msg: "Permission Denied",
+            })
+            .map_err(|e| e.extend())
+    }
+}
@@ -1 +1,20 @@
+use serde::{Deserialize, Serialize};
+
 pub mod api;
+pub mod auth;
+pub mod limits;
+pub mod metrics;
+pub mod persisted;
+
+/// Configuration of the GraphQL schema.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GraphqlConfig {
+    pub limits: limits::LimitsConfig,
+    #[serde(default)]
+    pub persisted_queries: persisted::PersistedQueriesConfig,
+    #[serde(default)]
+    pub allow_list: persisted::AllowListConfig,
+    /// Reject the introspection queries, which reveal the whole schema.
+    #[serde(default)]
+    pub disable_introspection: bool,
+}
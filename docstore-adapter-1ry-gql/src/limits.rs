@@ -0,0 +1,241 @@
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, Field, Selection, SelectionSet};
+use async_graphql::{Name, ServerError, ServerResult, Value, Variables};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Limits applied to every query executed by the schema.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LimitsConfig {
+    /// Maximum nesting depth of a query.
+    pub max_depth: usize,
+    /// Maximum cost of a query, as computed with `field_costs`.
+    pub max_complexity: usize,
+    /// Cost of a field, when it is not listed in `field_costs`.
+    pub default_field_cost: usize,
+    /// Cost of individual fields, indexed by field name (case insensitive).
+    #[serde(default)]
+    pub field_costs: HashMap<String, usize>,
+    /// Upper bound on the `limit` of a `ListDocumentsRequest`.
+    pub max_list_limit: u32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_depth: 10,
+            max_complexity: 1000,
+            default_field_cost: 1,
+            field_costs: HashMap::new(),
+            max_list_limit: 100,
+        }
+    }
+}
+
+// An extension which computes the cost of a query before it is executed, and
+// rejects it if it exceeds the configured maximum.
+// The cost of a field is its own cost (from the configuration), plus the cost of its
+// children. When the field is a list with a `request.limit` argument, the cost of its
+// children is multiplied by that limit.
+pub struct QueryCost {
+    limits: Arc<LimitsConfig>,
+}
+
+impl QueryCost {
+    pub fn new(limits: LimitsConfig) -> Self {
+        QueryCost {
+            limits: Arc::new(limits),
+        }
+    }
+}
+
+impl ExtensionFactory for QueryCost {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryCostExtension {
+            limits: self.limits.clone(),
+        })
+    }
+}
+
+struct QueryCostExtension {
+    limits: Arc<LimitsConfig>,
+}
+
+#[async_trait::async_trait]
+impl Extension for QueryCostExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+        let cost = query_cost(&self.limits, &document, variables);
+        if cost > self.limits.max_complexity {
+            return Err(ServerError::new(
+                format!(
+                    "Query is too complex: its cost is {}, and the maximum allowed is {}",
+                    cost, self.limits.max_complexity
+                ),
+                None,
+            ));
+        }
+        Ok(document)
+    }
+}
+
+/// Returns the cost of the most expensive operation in the document.
+pub fn query_cost(
+    limits: &LimitsConfig,
+    document: &ExecutableDocument,
+    variables: &Variables,
+) -> usize {
+    document
+        .operations
+        .iter()
+        .map(|(_, operation)| {
+            let mut visited = HashSet::new();
+            selection_set_cost(
+                limits,
+                document,
+                variables,
+                &operation.node.selection_set.node,
+                &mut visited,
+            )
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn selection_set_cost<'a>(
+    limits: &LimitsConfig,
+    document: &'a ExecutableDocument,
+    variables: &Variables,
+    selection_set: &'a SelectionSet,
+    visited: &mut HashSet<&'a Name>,
+) -> usize {
+    selection_set
+        .items
+        .iter()
+        .map(|selection| match &selection.node {
+            Selection::Field(field) => {
+                field_cost(limits, document, variables, &field.node, visited)
+            }
+            Selection::InlineFragment(fragment) => selection_set_cost(
+                limits,
+                document,
+                variables,
+                &fragment.node.selection_set.node,
+                visited,
+            ),
+            Selection::FragmentSpread(spread) => {
+                let name = &spread.node.fragment_name.node;
+                // Cycles in fragments are reported by the validation, we just need
+                // to make sure we don't loop forever.
+                if !visited.insert(name) {
+                    return 0;
+                }
+                let cost = document
+                    .fragments
+                    .get(name)
+                    .map(|fragment| {
+                        selection_set_cost(
+                            limits,
+                            document,
+                            variables,
+                            &fragment.node.selection_set.node,
+                            visited,
+                        )
+                    })
+                    .unwrap_or(0);
+                visited.remove(name);
+                cost
+            }
+        })
+        .fold(0usize, |acc, cost| acc.saturating_add(cost))
+}
+
+fn field_cost<'a>(
+    limits: &LimitsConfig,
+    document: &'a ExecutableDocument,
+    variables: &Variables,
+    field: &'a Field,
+    visited: &mut HashSet<&'a Name>,
+) -> usize {
+    // The configuration keys are lowercased, so we can't rely on a simple lookup.
+    let own = limits
+        .field_costs
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(field.name.node.as_str()))
+        .map(|(_, cost)| *cost)
+        .unwrap_or(limits.default_field_cost);
+    let children = selection_set_cost(
+        limits,
+        document,
+        variables,
+        &field.selection_set.node,
+        visited,
+    );
+    own.saturating_add(children.saturating_mul(list_limit(field, variables)))
+}
+
+// Returns the value of the `request.limit` argument, if there is one, 1 otherwise.
+fn list_limit(field: &Field, variables: &Variables) -> usize {
+    field
+        .get_argument("request")
+        .and_then(|request| {
+            request
+                .node
+                .clone()
+                .into_const_with(|name| variables.get(&name).cloned().ok_or(()))
+                .ok()
+        })
+        .and_then(|request| match request {
+            Value::Object(request) => request.get("limit").cloned(),
+            _ => None,
+        })
+        .and_then(|limit| match limit {
+            Value::Number(limit) => limit.as_u64(),
+            _ => None,
+        })
+        .map(|limit| limit.max(1) as usize)
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::parser::parse_query;
+
+    const LIST_DOCUMENTS: &str = r#"
+        query ListDocuments($request: ListDocumentsRequest!) {
+          listDocuments(request: $request) {
+            documents { id, title }
+            count
+          }
+        }"#;
+
+    #[test]
+    fn should_multiply_children_cost_by_list_limit() {
+        let limits = LimitsConfig::default();
+        let document = parse_query(LIST_DOCUMENTS).unwrap();
+        let variables = Variables::from_json(serde_json::json!({
+            "request": { "offset": 0, "limit": 10 }
+        }));
+        // listDocuments (1) + 10 * (documents (1) + id (1) + title (1) + count (1))
+        assert_eq!(query_cost(&limits, &document, &variables), 41);
+    }
+
+    #[test]
+    fn should_use_configured_field_costs() {
+        let mut limits = LimitsConfig::default();
+        limits.field_costs.insert(String::from("title"), 5);
+        let document = parse_query(LIST_DOCUMENTS).unwrap();
+        let variables = Variables::from_json(serde_json::json!({
+            "request": { "offset": 0, "limit": 1 }
+        }));
+        assert_eq!(query_cost(&limits, &document, &variables), 9);
+    }
+}
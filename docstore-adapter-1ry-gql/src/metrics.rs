@@ -0,0 +1,56 @@
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextResolve};
+use async_graphql::{ResolveInfo, ServerResult, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Receives the time taken by the resolvers. Implemented by the server, so that the
+/// schema does not depend on a particular metrics library.
+pub trait ResolverObserver: Send + Sync {
+    fn observe(&self, parent_type: &str, field: &str, duration: Duration, success: bool);
+}
+
+// An extension which times the resolvers of the root fields, ie the operations of the
+// API (eg. 'Query.listDocuments'). Nested fields are not timed, they are part of their
+// root field, and timing them would multiply the number of series.
+pub struct ResolverTiming {
+    observer: Arc<dyn ResolverObserver>,
+}
+
+impl ResolverTiming {
+    pub fn new(observer: Arc<dyn ResolverObserver>) -> Self {
+        ResolverTiming { observer }
+    }
+}
+
+impl ExtensionFactory for ResolverTiming {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ResolverTimingExtension {
+            observer: self.observer.clone(),
+        })
+    }
+}
+
+struct ResolverTimingExtension {
+    observer: Arc<dyn ResolverObserver>,
+}
+
+#[async_trait::async_trait]
+impl Extension for ResolverTimingExtension {
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.path_node.parent.is_some() {
+            return next.run(ctx, info).await;
+        }
+        let parent_type = info.parent_type.to_string();
+        let field = info.name.to_string();
+        let start = Instant::now();
+        let result = next.run(ctx, info).await;
+        self.observer
+            .observe(&parent_type, &field, start.elapsed(), result.is_ok());
+        result
+    }
+}
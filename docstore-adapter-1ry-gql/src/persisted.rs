@@ -0,0 +1,290 @@
+use async_graphql::extensions::apollo_persisted_queries::{
+    ApolloPersistedQueries, LruCacheStorage,
+};
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextPrepareRequest,
+};
+use async_graphql::parser::parse_query;
+use async_graphql::parser::types::{
+    Directive, DocumentOperations, OperationDefinition, Selection, SelectionSet,
+};
+use async_graphql::parser::Positioned;
+use async_graphql::{Name, Request, ServerError, ServerResult, Value};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Cannot read allow-list {}: {}", path.display(), source))]
+    ReadAllowList {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Automatic Persisted Queries: clients can send the sha256 hash of a query instead
+/// of the query itself, once the server has seen it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PersistedQueriesConfig {
+    pub enabled: bool,
+    /// Maximum number of queries kept in the cache.
+    pub cache_size: usize,
+}
+
+impl Default for PersistedQueriesConfig {
+    fn default() -> Self {
+        PersistedQueriesConfig {
+            enabled: false,
+            cache_size: 512,
+        }
+    }
+}
+
+impl PersistedQueriesConfig {
+    pub fn extension(&self) -> Option<ApolloPersistedQueries<LruCacheStorage>> {
+        self.enabled
+            .then(|| ApolloPersistedQueries::new(LruCacheStorage::new(self.cache_size)))
+    }
+}
+
+/// Strict mode: only the queries found in the allow-list directory can be executed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AllowListConfig {
+    pub enabled: bool,
+    /// Directory containing the allowed queries, one operation per '.gql' file.
+    pub path: PathBuf,
+}
+
+// An extension rejecting every query which is not in the allow-list.
+// This extension must be registered after the persisted queries extension, so
+// that hash lookups are resolved before we check the query.
+#[derive(Clone, Debug)]
+pub struct AllowList {
+    queries: Arc<HashSet<String>>,
+}
+
+impl AllowList {
+    /// Loads every '.gql' file found in the directory. Files which do not contain
+    /// an executable document (eg. the schema) are skipped.
+    pub fn from_dir(path: &Path) -> Result<Self, Error> {
+        let entries = std::fs::read_dir(path).context(ReadAllowList { path })?;
+        let mut queries = HashSet::new();
+        for entry in entries {
+            let path = entry.context(ReadAllowList { path })?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("gql") {
+                continue;
+            }
+            let query = std::fs::read_to_string(&path).context(ReadAllowList { path: &path })?;
+            match normalize(&query) {
+                Some(query) => {
+                    info!("allowing queries from {}", path.display());
+                    queries.insert(query);
+                }
+                None => warn!("skipping {}: not an executable document", path.display()),
+            }
+        }
+        Ok(AllowList {
+            queries: Arc::new(queries),
+        })
+    }
+
+    pub fn allows(&self, query: &str) -> bool {
+        normalize(query).map_or(false, |query| self.queries.contains(&query))
+    }
+}
+
+impl ExtensionFactory for AllowList {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(self.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for AllowList {
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        if !self.allows(&request.query) {
+            return Err(ServerError::new("Query is not in the allow-list", None));
+        }
+        next.run(ctx, request).await
+    }
+}
+
+// Prints a document in a canonical form, so that the comparison is not affected by
+// comments, commas, whitespaces, or the way the values are written (eg. block
+// strings). The document is the one given by the parser, or None when the query
+// cannot be parsed. Operations and fragments are sorted by name.
+fn normalize(query: &str) -> Option<String> {
+    let document = parse_query(query).ok()?;
+    let mut printed = String::new();
+    match &document.operations {
+        DocumentOperations::Single(operation) => print_operation(&mut printed, None, operation),
+        DocumentOperations::Multiple(operations) => {
+            let mut operations = operations.iter().collect::<Vec<_>>();
+            operations.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+            for (name, operation) in operations {
+                print_operation(&mut printed, Some(name), operation);
+            }
+        }
+    }
+    let mut fragments = document.fragments.iter().collect::<Vec<_>>();
+    fragments.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    for (name, fragment) in fragments {
+        let fragment = &fragment.node;
+        printed.push_str(&format!(
+            "fragment {} on {}",
+            name, fragment.type_condition.node.on.node
+        ));
+        print_directives(&mut printed, &fragment.directives);
+        print_selection_set(&mut printed, &fragment.selection_set.node);
+    }
+    Some(printed)
+}
+
+fn print_operation(
+    printed: &mut String,
+    name: Option<&Name>,
+    operation: &Positioned<OperationDefinition>,
+) {
+    let operation = &operation.node;
+    printed.push_str(&operation.ty.to_string());
+    if let Some(name) = name {
+        printed.push_str(&format!(" {}", name));
+    }
+    if !operation.variable_definitions.is_empty() {
+        let variables = operation
+            .variable_definitions
+            .iter()
+            .map(|variable| {
+                let variable = &variable.node;
+                match &variable.default_value {
+                    Some(value) => format!(
+                        "${}: {} = {}",
+                        variable.name.node, variable.var_type.node, value.node
+                    ),
+                    None => format!("${}: {}", variable.name.node, variable.var_type.node),
+                }
+            })
+            .collect::<Vec<_>>();
+        printed.push_str(&format!("({})", variables.join(", ")));
+    }
+    print_directives(printed, &operation.directives);
+    print_selection_set(printed, &operation.selection_set.node);
+}
+
+fn print_arguments(printed: &mut String, arguments: &[(Positioned<Name>, Positioned<Value>)]) {
+    if !arguments.is_empty() {
+        let arguments = arguments
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name.node, value.node))
+            .collect::<Vec<_>>();
+        printed.push_str(&format!("({})", arguments.join(", ")));
+    }
+}
+
+fn print_directives(printed: &mut String, directives: &[Positioned<Directive>]) {
+    for directive in directives {
+        printed.push_str(&format!(" @{}", directive.node.name.node));
+        print_arguments(printed, &directive.node.arguments);
+    }
+}
+
+fn print_selection_set(printed: &mut String, selection_set: &SelectionSet) {
+    if selection_set.items.is_empty() {
+        return;
+    }
+    printed.push_str(" {");
+    for selection in &selection_set.items {
+        printed.push(' ');
+        match &selection.node {
+            Selection::Field(field) => {
+                let field = &field.node;
+                if let Some(alias) = &field.alias {
+                    printed.push_str(&format!("{}: ", alias.node));
+                }
+                printed.push_str(field.name.node.as_str());
+                print_arguments(printed, &field.arguments);
+                print_directives(printed, &field.directives);
+                print_selection_set(printed, &field.selection_set.node);
+            }
+            Selection::FragmentSpread(spread) => {
+                printed.push_str(&format!("...{}", spread.node.fragment_name.node));
+                print_directives(printed, &spread.node.directives);
+            }
+            Selection::InlineFragment(fragment) => {
+                let fragment = &fragment.node;
+                printed.push_str("...");
+                if let Some(condition) = &fragment.type_condition {
+                    printed.push_str(&format!(" on {}", condition.node.on.node));
+                }
+                print_directives(printed, &fragment.directives);
+                print_selection_set(printed, &fragment.selection_set.node);
+            }
+        }
+    }
+    printed.push_str(" }");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_ignore_formatting_differences() {
+        let reference = r#"
+            query GetDocument($request: GetDocumentRequest!) {
+              getDocument(request: $request) {
+                document {
+                  id,
+                  title
+                }
+              }
+            }"#;
+        let compact = r#"query GetDocument($request:GetDocumentRequest!){
+            getDocument(request:$request){document{id title}}} # compact"#;
+        assert_eq!(normalize(reference), normalize(compact));
+        assert!(normalize(reference).is_some());
+    }
+
+    #[test]
+    fn should_compare_the_parsed_documents() {
+        // Strings are compared by value, whatever their syntax.
+        assert_eq!(
+            normalize(r#"{ search(text: """C# "tips" # and tricks""") { id } }"#),
+            normalize(r#"{ search(text: "C# \"tips\" # and tricks") { id } }"#)
+        );
+        assert_ne!(
+            normalize(r#"{ search(text: "C# tips") { id } }"#),
+            normalize(r#"{ search(text: "C") { id } }"#)
+        );
+        assert_eq!(
+            normalize(
+                "{ node { ...on Document { id } ...Fields } } fragment Fields on Node { id }"
+            ),
+            normalize(
+                "{ node { ... on Document { id }, ... Fields } }\nfragment Fields on Node { id }"
+            )
+        );
+        assert_eq!(normalize("{ node {"), None);
+    }
+
+    #[test]
+    fn should_allow_client_queries() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("docstore-client-gql")
+            .join("graphql");
+        let allow_list = AllowList::from_dir(&path).expect("allow-list");
+        let query = std::fs::read_to_string(path.join("list_documents.gql")).unwrap();
+        assert!(allow_list.allows(&query));
+        assert!(!allow_list.allows("{ __schema { types { name } } }"));
+    }
+}
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Utc};
+use docstore_domain::model;
+use docstore_domain::model::document::Document;
+use docstore_domain::model::error::{Error as ModelError, ErrorKind};
+use docstore_domain::model::event::{DocumentEvent, DocumentEventKind};
+use docstore_domain::model::validation::normalize_tag;
+use prost_types::Timestamp;
+use std::convert::TryFrom;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::proto;
+
+fn timestamp(date: DateTime<Utc>) -> Timestamp {
+    Timestamp {
+        seconds: date.timestamp(),
+        nanos: date.timestamp_subsec_nanos() as i32,
+    }
+}
+
+// Optional strings are sent as empty strings.
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+pub(crate) fn parse_id(id: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(id)
+        .map_err(|err| Status::invalid_argument(format!("Invalid id '{}': {}", id, err)))
+}
+
+impl From<&model::document::Genre> for proto::Genre {
+    fn from(genre: &model::document::Genre) -> Self {
+        match genre {
+            model::document::Genre::Tutorial => proto::Genre::Tutorial,
+            model::document::Genre::Howto => proto::Genre::Howto,
+            model::document::Genre::Background => proto::Genre::Background,
+            model::document::Genre::Reference => proto::Genre::Reference,
+            model::document::Genre::Tbd => proto::Genre::Tbd,
+        }
+    }
+}
+
+impl From<proto::Genre> for model::document::Genre {
+    fn from(genre: proto::Genre) -> Self {
+        match genre {
+            proto::Genre::Tutorial => model::document::Genre::Tutorial,
+            proto::Genre::Howto => model::document::Genre::Howto,
+            proto::Genre::Background => model::document::Genre::Background,
+            proto::Genre::Reference => model::document::Genre::Reference,
+            proto::Genre::Tbd => model::document::Genre::Tbd,
+        }
+    }
+}
+
+// Genres unknown to this version, sent by newer clients, are 'to be decided'.
+fn genre(value: i32) -> model::document::Genre {
+    proto::Genre::from_i32(value)
+        .unwrap_or(proto::Genre::Tbd)
+        .into()
+}
+
+impl From<Document> for proto::Document {
+    fn from(document: Document) -> Self {
+        let Document {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre,
+            created_at,
+            updated_at,
+            created_by,
+            updated_by,
+        } = document;
+
+        proto::Document {
+            id: id.to_string(),
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre: proto::Genre::from(&genre) as i32,
+            created_at: Some(timestamp(created_at)),
+            updated_at: Some(timestamp(updated_at)),
+            created_by: created_by.unwrap_or_default(),
+            updated_by: updated_by.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<DocumentEvent> for proto::DocumentEvent {
+    fn from(event: DocumentEvent) -> Self {
+        let DocumentEvent {
+            id,
+            kind,
+            document,
+            occurred_at,
+            actor,
+        } = event;
+        let kind = match kind {
+            DocumentEventKind::Created => proto::DocumentEventKind::Created,
+        };
+
+        proto::DocumentEvent {
+            id: id.to_string(),
+            kind: kind as i32,
+            document: Some(proto::Document::from(document)),
+            occurred_at: Some(timestamp(occurred_at)),
+            actor: actor.unwrap_or_default(),
+        }
+    }
+}
+
+// Scalars are never missing in proto3: a request without a limit has a limit of 0, which
+// is given the same default as the other APIs.
+const DEFAULT_LIMIT: u32 = 20;
+
+impl From<proto::ListDocumentsRequest> for model::document::ListDocumentsRequest {
+    fn from(request: proto::ListDocumentsRequest) -> Self {
+        let proto::ListDocumentsRequest {
+            offset,
+            limit,
+            genre_filter,
+            tag,
+        } = request;
+        model::document::ListDocumentsRequest {
+            offset,
+            limit: if limit == 0 { DEFAULT_LIMIT } else { limit },
+            genre: genre_filter
+                .map(|proto::list_documents_request::GenreFilter::Genre(value)| genre(value)),
+            tag: non_empty(tag).map(|tag| normalize_tag(&tag)),
+        }
+    }
+}
+
+impl TryFrom<proto::AddDocumentRequest> for model::document::AddDocumentRequest {
+    type Error = Status;
+
+    fn try_from(request: proto::AddDocumentRequest) -> Result<Self, Self::Error> {
+        let proto::AddDocumentRequest {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre: value,
+        } = request;
+        Ok(model::document::AddDocumentRequest {
+            id: non_empty(id).map(|id| parse_id(&id)).transpose()?,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre: genre(value),
+        })
+    }
+}
+
+/// Maps the errors of the model to the gRPC status codes.
+pub(crate) fn status(err: ModelError) -> Status {
+    let message = err.public_message();
+    match err.kind() {
+        ErrorKind::PermissionDenied => Status::permission_denied(message),
+        ErrorKind::Unauthenticated => Status::unauthenticated(message),
+        ErrorKind::NotFound => Status::not_found(message),
+        ErrorKind::Conflict => Status::already_exists(message),
+        ErrorKind::InvalidArgument => Status::invalid_argument(message),
+        ErrorKind::DeadlineExceeded => Status::deadline_exceeded(message),
+        ErrorKind::Internal => {
+            tracing::error!("gRPC request failed: {}", err);
+            Status::internal(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_convert_add_document_request() {
+        let request = proto::AddDocumentRequest {
+            id: String::new(),
+            title: String::from("Getting Started"),
+            outline: String::from("abstract"),
+            content: String::from("content"),
+            html: String::from("<p>content</p>"),
+            tags: vec![String::from("rust")],
+            genre: proto::Genre::Howto as i32,
+        };
+        let request = model::document::AddDocumentRequest::try_from(request).unwrap();
+        assert!(request.id.is_none());
+        assert!(matches!(request.genre, model::document::Genre::Howto));
+
+        let request = proto::AddDocumentRequest {
+            id: String::from("not-a-uuid"),
+            ..proto::AddDocumentRequest::default()
+        };
+        let status = model::document::AddDocumentRequest::try_from(request).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn should_default_a_missing_limit() {
+        let request = model::document::ListDocumentsRequest::from(proto::ListDocumentsRequest {
+            limit: 0,
+            ..proto::ListDocumentsRequest::default()
+        });
+        assert_eq!(request.limit, DEFAULT_LIMIT);
+        let request = model::document::ListDocumentsRequest::from(proto::ListDocumentsRequest {
+            limit: 5,
+            ..proto::ListDocumentsRequest::default()
+        });
+        assert_eq!(request.limit, 5);
+    }
+
+    #[test]
+    fn should_map_errors_to_status_codes() {
+        let code = |err| status(err).code();
+        assert_eq!(
+            code(ModelError::PermissionDenied {
+                subject: String::from("anonymous"),
+                permission: model::authorization::Permission::Write,
+            }),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            code(ModelError::InvalidApiKey),
+            tonic::Code::Unauthenticated
+        );
+        assert_eq!(code(ModelError::NotFound), tonic::Code::NotFound);
+        assert_eq!(
+            code(ModelError::Conflict { ids: Vec::new() }),
+            tonic::Code::AlreadyExists
+        );
+        assert_eq!(
+            code(ModelError::Validation { errors: Vec::new() }),
+            tonic::Code::InvalidArgument
+        );
+        assert_eq!(
+            code(ModelError::DeadlineExceeded),
+            tonic::Code::DeadlineExceeded
+        );
+
+        // The message of a storage error is not returned to the caller.
+        let status = status(ModelError::Storage {
+            source: Box::new(std::io::Error::new(std::io::ErrorKind::Other, "password")),
+        });
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert!(!status.message().contains("password"));
+    }
+}
@@ -0,0 +1,28 @@
+use docstore_domain::model::event::DocumentEvent;
+use docstore_domain::ports::secondary::events::EventPublisher;
+use tokio::sync::broadcast;
+
+/// Publishes the events to every subscriber of a broadcast channel. Subscribers which
+/// fall behind by more than `capacity` events miss the oldest ones.
+pub struct BroadcastPublisher {
+    sender: broadcast::Sender<DocumentEvent>,
+}
+
+impl BroadcastPublisher {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        BroadcastPublisher { sender }
+    }
+
+    /// Receives the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<DocumentEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl EventPublisher for BroadcastPublisher {
+    fn publish(&self, event: DocumentEvent) {
+        // Sending only fails when there is no subscriber.
+        let _ = self.sender.send(event);
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+mod convert;
+pub mod events;
+pub mod service;
+
+/// The code generated from 'proto/docstore.proto'.
+pub mod proto {
+    tonic::include_proto!("docstore.v1");
+}
+
+/// Configuration of the gRPC server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GrpcConfig {
+    /// Serve the gRPC API, next to the HTTP routes.
+    pub enabled: bool,
+    /// Port on which the gRPC API is served, on the same host as the HTTP routes.
+    pub port: u16,
+    /// Upper bound on the 'limit' given to 'ListDocuments'.
+    pub max_list_limit: u32,
+    /// Number of events kept for the 'WatchDocuments' streams which fall behind.
+    pub watch_capacity: usize,
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        GrpcConfig {
+            enabled: false,
+            port: 5051,
+            max_list_limit: 100,
+            watch_capacity: 256,
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use docstore_domain::model;
+use docstore_domain::model::authorization::Permission;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::ports::primary::storage::DocumentStorage;
+use docstore_domain::ports::secondary::authorization::AuthorizationPolicy;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+
+use crate::convert::{parse_id, status};
+use crate::events::BroadcastPublisher;
+use crate::proto;
+use crate::proto::documents_server::{Documents, DocumentsServer};
+use crate::GrpcConfig;
+
+/// What a call does to the documents, eg. for the rate limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Builds the context of a request from its metadata, authenticating the caller.
+/// Authentication is left to the server, so that the gRPC API accepts the same
+/// credentials, and applies the same rate limits, as the HTTP routes.
+#[async_trait]
+pub trait ContextProvider {
+    async fn context(
+        &self,
+        metadata: &MetadataMap,
+        remote: Option<SocketAddr>,
+        access: Access,
+    ) -> Result<RequestContext, Status>;
+}
+
+/// The 'Documents' gRPC service, which delegates to the primary port.
+pub struct DocumentsService {
+    service: Arc<dyn DocumentStorage + Send + Sync>,
+    policy: Arc<dyn AuthorizationPolicy + Send + Sync>,
+    events: Arc<BroadcastPublisher>,
+    contexts: Arc<dyn ContextProvider + Send + Sync>,
+    max_list_limit: u32,
+}
+
+impl DocumentsService {
+    /// The `events` must be published by `service`, so that 'WatchDocuments' streams
+    /// the changes made through every API.
+    pub fn new(
+        service: Arc<dyn DocumentStorage + Send + Sync>,
+        policy: Arc<dyn AuthorizationPolicy + Send + Sync>,
+        events: Arc<BroadcastPublisher>,
+        contexts: Arc<dyn ContextProvider + Send + Sync>,
+        config: &GrpcConfig,
+    ) -> Self {
+        DocumentsService {
+            service,
+            policy,
+            events,
+            contexts,
+            max_list_limit: config.max_list_limit,
+        }
+    }
+
+    pub fn into_server(self) -> DocumentsServer<Self> {
+        DocumentsServer::new(self)
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::DocumentEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Documents for DocumentsService {
+    async fn list_documents(
+        &self,
+        request: Request<proto::ListDocumentsRequest>,
+    ) -> Result<Response<proto::ListDocumentsResponse>, Status> {
+        let context = self
+            .contexts
+            .context(request.metadata(), request.remote_addr(), Access::Read)
+            .await?;
+        let request = model::document::ListDocumentsRequest::from(request.into_inner());
+        if request.limit > self.max_list_limit {
+            return Err(Status::invalid_argument(format!(
+                "Limit Exceeded: {} is above the maximum of {}",
+                request.limit, self.max_list_limit
+            )));
+        }
+        let documents = self
+            .service
+            .list_documents(&context, &request)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::ListDocumentsResponse {
+            documents: documents.into_iter().map(proto::Document::from).collect(),
+        }))
+    }
+
+    async fn get_document(
+        &self,
+        request: Request<proto::GetDocumentRequest>,
+    ) -> Result<Response<proto::Document>, Status> {
+        let context = self
+            .contexts
+            .context(request.metadata(), request.remote_addr(), Access::Read)
+            .await?;
+        let request = model::document::GetDocumentRequest {
+            id: parse_id(&request.get_ref().id)?,
+        };
+        let document = self
+            .service
+            .get_document(&context, &request)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::Document::from(document)))
+    }
+
+    async fn add_document(
+        &self,
+        request: Request<proto::AddDocumentRequest>,
+    ) -> Result<Response<proto::Document>, Status> {
+        let context = self
+            .contexts
+            .context(request.metadata(), request.remote_addr(), Access::Write)
+            .await?;
+        let request = model::document::AddDocumentRequest::try_from(request.into_inner())?;
+        let document = self
+            .service
+            .add_document(&context, &request)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::Document::from(document)))
+    }
+
+    type WatchDocumentsStream = EventStream;
+
+    async fn watch_documents(
+        &self,
+        request: Request<proto::WatchDocumentsRequest>,
+    ) -> Result<Response<Self::WatchDocumentsStream>, Status> {
+        let context = self
+            .contexts
+            .context(request.metadata(), request.remote_addr(), Access::Read)
+            .await?;
+        self.policy
+            .authorize(&context.principal, Permission::Read)
+            .map_err(status)?;
+        // A stream which falls behind skips the events it missed.
+        let events = BroadcastStream::new(self.events.subscribe()).filter_map(|event| {
+            event
+                .ok()
+                .map(|event| Ok(proto::DocumentEvent::from(event)))
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use docstore_domain::model::error::Error as ModelError;
+    use docstore_domain::ports::primary::storage::MockDocumentStorage;
+    use docstore_domain::ports::secondary::authorization::MockAuthorizationPolicy;
+
+    struct Anonymous;
+
+    #[async_trait]
+    impl ContextProvider for Anonymous {
+        async fn context(
+            &self,
+            _metadata: &MetadataMap,
+            _remote: Option<SocketAddr>,
+            _access: Access,
+        ) -> Result<RequestContext, Status> {
+            Ok(RequestContext::default())
+        }
+    }
+
+    // Records the access of each call, and refuses it.
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<Access>>);
+
+    #[async_trait]
+    impl ContextProvider for Recorder {
+        async fn context(
+            &self,
+            _metadata: &MetadataMap,
+            _remote: Option<SocketAddr>,
+            access: Access,
+        ) -> Result<RequestContext, Status> {
+            self.0.lock().unwrap().push(access);
+            Err(Status::resource_exhausted("Too Many Requests"))
+        }
+    }
+
+    fn service(storage: MockDocumentStorage) -> DocumentsService {
+        service_with(storage, Arc::new(Anonymous))
+    }
+
+    fn service_with(
+        storage: MockDocumentStorage,
+        contexts: Arc<dyn ContextProvider + Send + Sync>,
+    ) -> DocumentsService {
+        DocumentsService::new(
+            Arc::new(storage),
+            Arc::new(MockAuthorizationPolicy::new()),
+            Arc::new(BroadcastPublisher::new(1)),
+            contexts,
+            &GrpcConfig::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_tell_the_access_of_each_call() {
+        let recorder = Arc::new(Recorder::default());
+        let service = service_with(MockDocumentStorage::new(), recorder.clone());
+        let request = Request::new(proto::ListDocumentsRequest::default());
+        let status = service.list_documents(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        let request = Request::new(proto::AddDocumentRequest::default());
+        let status = service.add_document(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![Access::Read, Access::Write]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_list_documents_with_the_default_limit() {
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_list_documents()
+            .withf(|_, request| request.limit == 20)
+            .times(1)
+            .returning(|_, _| Ok(Vec::new()));
+
+        let request = Request::new(proto::ListDocumentsRequest::default());
+        let response = service(storage).list_documents(request).await.unwrap();
+        assert!(response.into_inner().documents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_limit_above_the_maximum() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_list_documents().never();
+
+        let request = Request::new(proto::ListDocumentsRequest {
+            limit: 1000,
+            ..proto::ListDocumentsRequest::default()
+        });
+        let status = service(storage).list_documents(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn should_report_invalid_and_unknown_ids() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_get_document().never();
+        let request = Request::new(proto::GetDocumentRequest {
+            id: String::from("not-a-uuid"),
+        });
+        let status = service(storage).get_document(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_get_document()
+            .times(1)
+            .returning(|_, _| Err(ModelError::NotFound));
+        let request = Request::new(proto::GetDocumentRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+        });
+        let status = service(storage).get_document(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,559 @@
+use chrono::{DateTime, Utc};
+use docstore_domain::model;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::document::Document;
+use docstore_domain::model::error::{Error as ModelError, ErrorKind};
+use docstore_domain::model::validation::{normalize_tag, FieldError};
+use docstore_domain::ports::primary::storage::DocumentStorage;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::hyper::body::Bytes;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use crate::openapi;
+use crate::RestConfig;
+
+type Service = Arc<dyn DocumentStorage + Send + Sync>;
+
+// The types below mirror the model's types, so that they can derive JsonSchema
+// without making the model depend on the adapters.
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Genre {
+    Tutorial,
+    Howto,
+    Background,
+    Reference,
+    Tbd,
+}
+
+impl Default for Genre {
+    fn default() -> Self {
+        Genre::Tbd
+    }
+}
+
+impl From<Genre> for model::document::Genre {
+    fn from(genre: Genre) -> Self {
+        match genre {
+            Genre::Tutorial => model::document::Genre::Tutorial,
+            Genre::Howto => model::document::Genre::Howto,
+            Genre::Background => model::document::Genre::Background,
+            Genre::Reference => model::document::Genre::Reference,
+            Genre::Tbd => model::document::Genre::Tbd,
+        }
+    }
+}
+
+impl From<&model::document::Genre> for Genre {
+    fn from(genre: &model::document::Genre) -> Self {
+        match genre {
+            model::document::Genre::Tutorial => Genre::Tutorial,
+            model::document::Genre::Howto => Genre::Howto,
+            model::document::Genre::Background => Genre::Background,
+            model::document::Genre::Reference => Genre::Reference,
+            model::document::Genre::Tbd => Genre::Tbd,
+        }
+    }
+}
+
+/// The query string of 'GET /documents'.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ListDocumentsQuery {
+    /// Number of documents to skip.
+    #[serde(default)]
+    pub offset: u32,
+    /// Maximum number of documents to return.
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    /// Only list the documents of this genre.
+    pub genre: Option<Genre>,
+    /// Only list the documents with this tag.
+    pub tag: Option<String>,
+}
+
+fn default_limit() -> u32 {
+    20
+}
+
+impl From<ListDocumentsQuery> for model::document::ListDocumentsRequest {
+    fn from(query: ListDocumentsQuery) -> Self {
+        let ListDocumentsQuery {
+            offset,
+            limit,
+            genre,
+            tag,
+        } = query;
+        model::document::ListDocumentsRequest {
+            offset,
+            limit,
+            genre: genre.map(model::document::Genre::from),
+            tag: tag.as_deref().map(normalize_tag),
+        }
+    }
+}
+
+/// The body of 'POST /documents'.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AddDocumentRequest {
+    /// The id of the new document, generated by the server if it is not given.
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub title: String,
+    #[serde(rename = "abstract")]
+    pub outline: String,
+    pub content: String,
+    pub html: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub genre: Genre,
+}
+
+impl From<AddDocumentRequest> for model::document::AddDocumentRequest {
+    fn from(request: AddDocumentRequest) -> Self {
+        let AddDocumentRequest {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre,
+        } = request;
+        model::document::AddDocumentRequest {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre: genre.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentResponse {
+    pub id: Uuid,
+    pub title: String,
+    #[serde(rename = "abstract")]
+    pub outline: String,
+    pub content: String,
+    pub html: String,
+    pub tags: Vec<String>,
+    pub genre: Genre,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Subject of the principal who created the document.
+    pub author: Option<String>,
+}
+
+impl From<Document> for DocumentResponse {
+    fn from(document: Document) -> Self {
+        let Document {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre,
+            created_at,
+            updated_at,
+            created_by,
+            ..
+        } = document;
+
+        DocumentResponse {
+            id,
+            title,
+            outline,
+            content,
+            html,
+            tags,
+            genre: Genre::from(&genre),
+            created_at,
+            updated_at,
+            author: created_by,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ListDocumentsResponse {
+    pub documents: Vec<DocumentResponse>,
+    pub count: usize,
+}
+
+impl From<Vec<Document>> for ListDocumentsResponse {
+    fn from(documents: Vec<Document>) -> Self {
+        let documents = documents
+            .into_iter()
+            .map(DocumentResponse::from)
+            .collect::<Vec<_>>();
+        let count = documents.len();
+        ListDocumentsResponse { documents, count }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct FieldErrorResponse {
+    pub field: String,
+    pub message: String,
+}
+
+impl From<FieldError> for FieldErrorResponse {
+    fn from(error: FieldError) -> Self {
+        let FieldError { field, message } = error;
+        FieldErrorResponse { field, message }
+    }
+}
+
+/// The body of every error response.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ErrorResponse {
+    /// The same codes as the GraphQL API, eg 'FORBIDDEN', or 'VALIDATION_FAILED'.
+    pub code: String,
+    pub message: String,
+    /// The invalid fields, when the code is 'VALIDATION_FAILED'.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldErrorResponse>,
+}
+
+/// The routes of the REST API, mounted under the configured prefix:
+/// - 'GET /documents' lists the documents, the most recently created first,
+/// - 'GET /documents/{id}' returns a document,
+/// - 'POST /documents' adds a document,
+/// - 'GET /openapi.json' returns the OpenAPI document describing these routes.
+///
+/// Requests are served with the context extracted by `context`, which is also
+/// responsible for rejecting unauthenticated callers.
+pub fn routes<C>(
+    service: Service,
+    config: &RestConfig,
+    context: C,
+    content_length_limit: u64,
+) -> BoxedFilter<(Response,)>
+where
+    C: Filter<Extract = (RequestContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+{
+    let prefix = config
+        .segments()
+        .into_iter()
+        .fold(warp::any().boxed(), |prefix, segment| {
+            prefix.and(warp::path(segment)).boxed()
+        });
+    let with_service = warp::any().map(move || service.clone());
+    let max_list_limit = config.max_list_limit;
+    let location = format!("{}/documents", config.base_path());
+    let specification = Arc::new(openapi::document(config));
+
+    // The query string is parsed by the handler, so that an invalid query is
+    // answered with a 400 instead of a rejection.
+    let query = warp::query::raw().or(warp::any().map(String::new)).unify();
+
+    let list = prefix
+        .clone()
+        .and(warp::path!("documents"))
+        .and(warp::get())
+        .and(query)
+        .and(context.clone())
+        .and(with_service.clone())
+        .and_then(move |query, context, service| {
+            list_documents(service, context, query, max_list_limit)
+        });
+
+    let get = prefix
+        .clone()
+        .and(warp::path!("documents" / String))
+        .and(warp::get())
+        .and(context.clone())
+        .and(with_service.clone())
+        .and_then(|id, context, service| get_document(service, context, id));
+
+    let add = prefix
+        .clone()
+        .and(warp::path!("documents"))
+        .and(warp::post())
+        .and(warp::body::content_length_limit(content_length_limit))
+        .and(warp::body::bytes())
+        .and(context)
+        .and(with_service)
+        .and_then(move |body, context, service| {
+            add_document(service, context, body, location.clone())
+        });
+
+    let openapi = prefix
+        .and(warp::path!("openapi.json"))
+        .and(warp::get())
+        .map(move || warp::reply::json(specification.as_ref()).into_response());
+
+    list.or(get)
+        .unify()
+        .or(add)
+        .unify()
+        .or(openapi)
+        .unify()
+        .boxed()
+}
+
+async fn list_documents(
+    service: Service,
+    context: RequestContext,
+    query: String,
+    max_list_limit: u32,
+) -> Result<Response, Infallible> {
+    let query: ListDocumentsQuery = match serde_urlencoded::from_str(&query) {
+        Ok(query) => query,
+        Err(err) => return Ok(bad_request("BAD_USER_INPUT", err.to_string())),
+    };
+    if query.limit > max_list_limit {
+        return Ok(bad_request(
+            "LIMIT_EXCEEDED",
+            format!(
+                "Limit Exceeded: {} is above the maximum of {}",
+                query.limit, max_list_limit
+            ),
+        ));
+    }
+    let request = model::document::ListDocumentsRequest::from(query);
+    Ok(match service.list_documents(&context, &request).await {
+        Ok(documents) => json(StatusCode::OK, &ListDocumentsResponse::from(documents)),
+        Err(err) => error(err),
+    })
+}
+
+async fn get_document(
+    service: Service,
+    context: RequestContext,
+    id: String,
+) -> Result<Response, Infallible> {
+    let id = match Uuid::parse_str(&id) {
+        Ok(id) => id,
+        Err(err) => return Ok(bad_request("BAD_USER_INPUT", err.to_string())),
+    };
+    let request = model::document::GetDocumentRequest { id };
+    Ok(match service.get_document(&context, &request).await {
+        Ok(document) => json(StatusCode::OK, &DocumentResponse::from(document)),
+        Err(err) => error(err),
+    })
+}
+
+async fn add_document(
+    service: Service,
+    context: RequestContext,
+    body: Bytes,
+    location: String,
+) -> Result<Response, Infallible> {
+    let request: AddDocumentRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(err) => return Ok(bad_request("BAD_USER_INPUT", err.to_string())),
+    };
+    let request = model::document::AddDocumentRequest::from(request);
+    Ok(match service.add_document(&context, &request).await {
+        Ok(document) => {
+            let location = format!("{}/{}", location, document.id);
+            let response = json(StatusCode::CREATED, &DocumentResponse::from(document));
+            warp::reply::with_header(response, "location", location).into_response()
+        }
+        Err(err) => error(err),
+    })
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response {
+    warp::reply::with_status(warp::reply::json(body), status).into_response()
+}
+
+fn bad_request(code: &str, message: String) -> Response {
+    let body = ErrorResponse {
+        code: code.to_string(),
+        message,
+        fields: Vec::new(),
+    };
+    json(StatusCode::BAD_REQUEST, &body)
+}
+
+fn error(err: ModelError) -> Response {
+    let (status, code) = match err.kind() {
+        ErrorKind::PermissionDenied => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+        ErrorKind::Unauthenticated => (StatusCode::UNAUTHORIZED, "UNAUTHENTICATED"),
+        ErrorKind::NotFound => (StatusCode::NOT_FOUND, "NOT_FOUND"),
+        ErrorKind::Conflict => (StatusCode::CONFLICT, "CONFLICT"),
+        ErrorKind::InvalidArgument => (StatusCode::UNPROCESSABLE_ENTITY, "VALIDATION_FAILED"),
+        ErrorKind::DeadlineExceeded => (StatusCode::GATEWAY_TIMEOUT, "DEADLINE_EXCEEDED"),
+        ErrorKind::Internal => {
+            tracing::error!("REST request failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_SERVER_ERROR")
+        }
+    };
+    let message = err.public_message();
+    let fields = match err {
+        ModelError::Validation { errors } => {
+            errors.into_iter().map(FieldErrorResponse::from).collect()
+        }
+        _ => Vec::new(),
+    };
+    let body = ErrorResponse {
+        code: code.to_string(),
+        message,
+        fields,
+    };
+    json(status, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use docstore_domain::ports::primary::storage::MockDocumentStorage;
+
+    fn document(id: Uuid) -> Document {
+        let now = Utc::now();
+        Document {
+            id,
+            title: String::from("Getting Started"),
+            outline: String::from("abstract"),
+            content: String::from("content"),
+            html: String::from("<p>content</p>"),
+            tags: Vec::new(),
+            genre: model::document::Genre::Tutorial,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn filter(storage: MockDocumentStorage) -> BoxedFilter<(Response,)> {
+        let context = warp::any().map(RequestContext::default);
+        routes(Arc::new(storage), &RestConfig::default(), context, 1024)
+    }
+
+    fn body(response: &http::Response<Bytes>) -> ErrorResponse {
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[test]
+    fn should_parse_list_query_with_defaults() {
+        let query: ListDocumentsQuery = serde_urlencoded::from_str("genre=howto&tag=Rust").unwrap();
+        let request = model::document::ListDocumentsRequest::from(query);
+        assert_eq!(request.offset, 0);
+        assert_eq!(request.limit, 20);
+        assert!(matches!(request.genre, Some(model::document::Genre::Howto)));
+        assert_eq!(request.tag.as_deref(), Some("rust"));
+    }
+
+    #[tokio::test]
+    async fn should_locate_the_added_document() {
+        let id = Uuid::new_v4();
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_add_document()
+            .times(1)
+            .returning(move |_, _| Ok(document(id)));
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/v1/documents")
+            .json(&serde_json::json!({
+                "title": "Getting Started",
+                "abstract": "abstract",
+                "content": "content",
+                "html": "<p>content</p>",
+            }))
+            .reply(&filter(storage))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers()["location"],
+            format!("/api/v1/documents/{}", id).as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_find_an_unknown_document() {
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_get_document()
+            .returning(|_, _| Err(ModelError::NotFound));
+
+        let response = warp::test::request()
+            .path(&format!("/api/v1/documents/{}", Uuid::new_v4()))
+            .reply(&filter(storage))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body(&response).code, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_malformed_request() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_get_document().never();
+        storage.expect_add_document().never();
+        let filter = filter(storage);
+
+        let response = warp::test::request()
+            .path("/api/v1/documents/not-a-uuid")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body(&response).code, "BAD_USER_INPUT");
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/v1/documents")
+            .body("{\"title\":")
+            .reply(&filter)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body(&response).code, "BAD_USER_INPUT");
+    }
+
+    #[tokio::test]
+    async fn should_report_the_invalid_fields() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_add_document().returning(|_, _| {
+            Err(ModelError::Validation {
+                errors: vec![FieldError::new("title", "must not be empty")],
+            })
+        });
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/v1/documents")
+            .json(&serde_json::json!({
+                "title": "",
+                "abstract": "abstract",
+                "content": "content",
+                "html": "<p>content</p>",
+            }))
+            .reply(&filter(storage))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body(&response);
+        assert_eq!(body.code, "VALIDATION_FAILED");
+        assert_eq!(body.fields.len(), 1);
+        assert_eq!(body.fields[0].field, "title");
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_limit_above_the_maximum() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_list_documents().never();
+
+        let response = warp::test::request()
+            .path("/api/v1/documents?limit=1000")
+            .reply(&filter(storage))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body(&response).code, "LIMIT_EXCEEDED");
+    }
+}
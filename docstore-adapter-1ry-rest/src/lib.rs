@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+pub mod api;
+pub mod openapi;
+
+/// Configuration of the REST API.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RestConfig {
+    /// Path under which the routes are mounted, eg '/api/v1'.
+    pub prefix: String,
+    /// Upper bound on the 'limit' given to 'GET /documents'.
+    pub max_list_limit: u32,
+}
+
+impl Default for RestConfig {
+    fn default() -> Self {
+        RestConfig {
+            prefix: String::from("/api/v1"),
+            max_list_limit: 100,
+        }
+    }
+}
+
+impl RestConfig {
+    // The segments of the prefix, without the empty ones, so that '/api/v1/' and
+    // 'api/v1' are the same prefix.
+    pub(crate) fn segments(&self) -> Vec<String> {
+        self.prefix
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// The prefix, as it appears in URLs: '/api/v1', or '' when there is none.
+    pub fn base_path(&self) -> String {
+        self.segments()
+            .iter()
+            .map(|segment| format!("/{}", segment))
+            .collect()
+    }
+}
@@ -0,0 +1,155 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+use crate::api::{
+    AddDocumentRequest, DocumentResponse, ErrorResponse, ListDocumentsQuery, ListDocumentsResponse,
+};
+use crate::RestConfig;
+
+/// Generates the OpenAPI 3 document describing the REST API. The schemas of the
+/// requests and of the responses, as well as the query parameters, are derived from
+/// their types, so the document cannot drift away from the implementation.
+pub fn document(config: &RestConfig) -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let parameters = query_parameters::<ListDocumentsQuery>(&mut generator);
+    let document = generator.subschema_for::<DocumentResponse>();
+    let documents = generator.subschema_for::<ListDocumentsResponse>();
+    let request = generator.subschema_for::<AddDocumentRequest>();
+    let error = generator.subschema_for::<ErrorResponse>();
+    let schemas = generator.take_definitions();
+
+    let content = |schema: &Schema| json!({ "application/json": { "schema": schema } });
+    let response = |description: &str, schema: &Schema| json!({ "description": description, "content": content(schema) });
+
+    let list_documents = json!({
+        "operationId": "listDocuments",
+        "summary": "Lists the documents, the most recently created first",
+        "parameters": parameters,
+        "responses": {
+            "200": response("The documents", &documents),
+            "400": response("Invalid parameters", &error),
+            "403": response("The caller may not read documents", &error),
+        }
+    });
+    let add_document = json!({
+        "operationId": "addDocument",
+        "summary": "Adds a document",
+        "requestBody": { "required": true, "content": content(&request) },
+        "responses": {
+            "201": {
+                "description": "The document was added",
+                "headers": {
+                    "Location": {
+                        "description": "URL of the document",
+                        "schema": { "type": "string" }
+                    }
+                },
+                "content": content(&document),
+            },
+            "400": response("Invalid JSON", &error),
+            "403": response("The caller may not add documents", &error),
+            "422": response("The document is invalid", &error),
+        }
+    });
+    let get_document = json!({
+        "operationId": "getDocument",
+        "summary": "Returns a document",
+        "parameters": [{
+            "name": "id",
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string", "format": "uuid" }
+        }],
+        "responses": {
+            "200": response("The document", &document),
+            "400": response("Invalid id", &error),
+            "403": response("The caller may not read documents", &error),
+            "404": response("There is no document with this id", &error),
+        }
+    });
+
+    let base_path = config.base_path();
+    let server = if base_path.is_empty() {
+        "/"
+    } else {
+        &base_path
+    };
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Docstore",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": server }],
+        "paths": {
+            "/documents": { "get": list_documents, "post": add_document },
+            "/documents/{id}": { "get": get_document },
+        },
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "x-api-key" },
+            }
+        },
+        // Anonymous callers are allowed, with the roles given to anonymous callers.
+        "security": [{ "bearer": [] }, { "apiKey": [] }, {}],
+    })
+}
+
+// Query parameters are described by the properties of the type the query string is
+// parsed into.
+fn query_parameters<T: JsonSchema>(generator: &mut SchemaGenerator) -> Vec<Value> {
+    let object = match generator.root_schema_for::<T>().schema.object {
+        Some(object) => object,
+        None => return Vec::new(),
+    };
+    object
+        .properties
+        .iter()
+        .map(|(name, schema)| {
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(name),
+                "schema": schema,
+            });
+            if let Schema::Object(schema) = schema {
+                if let Some(description) = schema
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.description.clone())
+                {
+                    parameter["description"] = Value::from(description);
+                }
+            }
+            parameter
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_describe_routes_and_schemas() {
+        let document = document(&RestConfig::default());
+        assert_eq!(document["servers"][0]["url"], "/api/v1");
+        assert!(document["paths"]["/documents"]["post"].is_object());
+        assert!(document["paths"]["/documents/{id}"]["get"].is_object());
+        assert!(document["components"]["schemas"]["DocumentResponse"].is_object());
+        assert!(document["components"]["schemas"]["Genre"].is_object());
+
+        let parameters = document["paths"]["/documents"]["get"]["parameters"]
+            .as_array()
+            .unwrap();
+        let names = parameters
+            .iter()
+            .map(|parameter| parameter["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["genre", "limit", "offset", "tag"]);
+    }
+}
@@ -0,0 +1,52 @@
+-- API keys used by services to authenticate. Only the sha256 hash of the
+-- secret is stored.
+CREATE TABLE IF NOT EXISTS main.api_keys (
+  id UUID PRIMARY KEY,
+  name TEXT NOT NULL,
+  hash TEXT NOT NULL UNIQUE,
+  scopes TEXT[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMPTZ,
+  revoked_at TIMESTAMPTZ
+);
+
+CREATE OR REPLACE FUNCTION api.create_api_key (
+  _id UUID
+, _name TEXT
+, _hash TEXT
+, _scopes TEXT[]
+, _created_at TIMESTAMPTZ
+, _expires_at TIMESTAMPTZ
+) RETURNS TABLE (id UUID, name TEXT, scopes TEXT[], created_at TIMESTAMPTZ, expires_at TIMESTAMPTZ, revoked_at TIMESTAMPTZ)
+AS $$
+  INSERT INTO main.api_keys (id, name, hash, scopes, created_at, expires_at)
+  VALUES (_id, _name, _hash, _scopes, _created_at, _expires_at)
+  RETURNING id, name, scopes, created_at, expires_at, revoked_at;
+$$ LANGUAGE SQL;
+
+CREATE OR REPLACE FUNCTION api.revoke_api_key (
+  _id UUID
+) RETURNS TABLE (id UUID, name TEXT, scopes TEXT[], created_at TIMESTAMPTZ, expires_at TIMESTAMPTZ, revoked_at TIMESTAMPTZ)
+AS $$
+  UPDATE main.api_keys k
+  SET revoked_at = COALESCE(k.revoked_at, NOW())
+  WHERE k.id = _id
+  RETURNING k.id, k.name, k.scopes, k.created_at, k.expires_at, k.revoked_at;
+$$ LANGUAGE SQL;
+
+CREATE OR REPLACE FUNCTION api.list_api_keys ()
+RETURNS TABLE (id UUID, name TEXT, scopes TEXT[], created_at TIMESTAMPTZ, expires_at TIMESTAMPTZ, revoked_at TIMESTAMPTZ)
+AS $$
+  SELECT k.id, k.name, k.scopes, k.created_at, k.expires_at, k.revoked_at
+  FROM main.api_keys k
+  ORDER BY k.created_at;
+$$ LANGUAGE SQL;
+
+CREATE OR REPLACE FUNCTION api.find_api_key (
+  _hash TEXT
+) RETURNS TABLE (id UUID, name TEXT, scopes TEXT[], created_at TIMESTAMPTZ, expires_at TIMESTAMPTZ, revoked_at TIMESTAMPTZ)
+AS $$
+  SELECT k.id, k.name, k.scopes, k.created_at, k.expires_at, k.revoked_at
+  FROM main.api_keys k
+  WHERE k.hash = _hash;
+$$ LANGUAGE SQL;
@@ -0,0 +1,56 @@
+-- Attribute documents to the principal who created / last updated them.
+ALTER TABLE main.documents
+  ADD COLUMN IF NOT EXISTS created_by TEXT,
+  ADD COLUMN IF NOT EXISTS updated_by TEXT;
+
+CREATE INDEX IF NOT EXISTS documents_created_by_idx ON main.documents (created_by);
+
+-- The returned columns have changed, so the functions must be dropped first.
+DROP FUNCTION IF EXISTS api.list_documents(INTEGER, INTEGER);
+DROP FUNCTION IF EXISTS api.get_document_by_id(UUID);
+DROP FUNCTION IF EXISTS api.add_document(UUID, TEXT, TEXT, TEXT, TEXT, TEXT[], main.GENRE);
+
+CREATE FUNCTION api.list_documents (
+  _limit INTEGER
+, _offset INTEGER
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  ORDER BY created_at DESC
+  LIMIT _limit OFFSET _offset;
+$$ LANGUAGE SQL;
+
+CREATE FUNCTION api.list_documents_by_author (
+  _author TEXT
+, _limit INTEGER
+, _offset INTEGER
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  WHERE created_by = _author
+  ORDER BY created_at DESC
+  LIMIT _limit OFFSET _offset;
+$$ LANGUAGE SQL;
+
+CREATE FUNCTION api.get_document_by_id (
+  _id UUID
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents WHERE id = _id;
+$$ LANGUAGE SQL;
+
+CREATE FUNCTION api.add_document (
+  _id UUID
+, _title TEXT
+, _outline TEXT
+, _content TEXT
+, _html TEXT
+, _tags TEXT[]
+, _genre main.GENRE
+, _author TEXT
+) RETURNS SETOF main.documents
+AS $$
+  INSERT INTO main.documents (id, title, outline, content, html, tags, genre, created_by, updated_by)
+  VALUES (_id, _title, _outline, _content, _html, _tags, _genre, _author, _author)
+  RETURNING *;
+$$ LANGUAGE SQL;
@@ -0,0 +1,22 @@
+-- The timestamps and the authors of a new document are supplied by the domain, so that
+-- they do not depend on the database clock.
+DROP FUNCTION IF EXISTS api.add_document(UUID, TEXT, TEXT, TEXT, TEXT, TEXT[], main.GENRE, TEXT);
+
+CREATE FUNCTION api.add_document (
+  _id UUID
+, _title TEXT
+, _outline TEXT
+, _content TEXT
+, _html TEXT
+, _tags TEXT[]
+, _genre main.GENRE
+, _created_at TIMESTAMPTZ
+, _updated_at TIMESTAMPTZ
+, _created_by TEXT
+, _updated_by TEXT
+) RETURNS SETOF main.documents
+AS $$
+  INSERT INTO main.documents (id, title, outline, content, html, tags, genre, created_at, updated_at, created_by, updated_by)
+  VALUES (_id, _title, _outline, _content, _html, _tags, _genre, _created_at, _updated_at, _created_by, _updated_by)
+  RETURNING *;
+$$ LANGUAGE SQL;
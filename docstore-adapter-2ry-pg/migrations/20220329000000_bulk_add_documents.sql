@@ -0,0 +1,28 @@
+-- Adds a batch of documents. Each argument holds one column of the batch. Since
+-- PostgreSQL arrays cannot be ragged, the tags of each document are given as a JSON
+-- array.
+-- Documents whose id already exists are skipped, so the caller can tell the conflicts
+-- from the returned rows.
+CREATE FUNCTION api.add_documents (
+  _ids UUID[]
+, _titles TEXT[]
+, _outlines TEXT[]
+, _contents TEXT[]
+, _htmls TEXT[]
+, _tags TEXT[]
+, _genres TEXT[]
+, _created_at TIMESTAMPTZ[]
+, _updated_at TIMESTAMPTZ[]
+, _created_by TEXT[]
+, _updated_by TEXT[]
+) RETURNS SETOF main.documents
+AS $$
+  INSERT INTO main.documents (id, title, outline, content, html, tags, genre, created_at, updated_at, created_by, updated_by)
+  SELECT d.id, d.title, d.outline, d.content, d.html
+       , ARRAY(SELECT jsonb_array_elements_text(d.tags::JSONB))
+       , d.genre::main.GENRE, d.created_at, d.updated_at, d.created_by, d.updated_by
+  FROM UNNEST(_ids, _titles, _outlines, _contents, _htmls, _tags, _genres, _created_at, _updated_at, _created_by, _updated_by)
+    AS d(id, title, outline, content, html, tags, genre, created_at, updated_at, created_by, updated_by)
+  ON CONFLICT (id) DO NOTHING
+  RETURNING *;
+$$ LANGUAGE SQL;
@@ -0,0 +1,39 @@
+-- Restores a batch of documents as they are (see api.add_documents for the format of
+-- the arguments). Existing documents are replaced when _overwrite is set, and skipped
+-- otherwise. The returned rows tell which documents were inserted, and which were
+-- replaced.
+CREATE FUNCTION api.import_documents (
+  _ids UUID[]
+, _titles TEXT[]
+, _outlines TEXT[]
+, _contents TEXT[]
+, _htmls TEXT[]
+, _tags TEXT[]
+, _genres TEXT[]
+, _created_at TIMESTAMPTZ[]
+, _updated_at TIMESTAMPTZ[]
+, _created_by TEXT[]
+, _updated_by TEXT[]
+, _overwrite BOOLEAN
+) RETURNS TABLE (id UUID, inserted BOOLEAN)
+AS $$
+  INSERT INTO main.documents AS doc (id, title, outline, content, html, tags, genre, created_at, updated_at, created_by, updated_by)
+  SELECT d.id, d.title, d.outline, d.content, d.html
+       , ARRAY(SELECT jsonb_array_elements_text(d.tags::JSONB))
+       , d.genre::main.GENRE, d.created_at, d.updated_at, d.created_by, d.updated_by
+  FROM UNNEST(_ids, _titles, _outlines, _contents, _htmls, _tags, _genres, _created_at, _updated_at, _created_by, _updated_by)
+    AS d(id, title, outline, content, html, tags, genre, created_at, updated_at, created_by, updated_by)
+  ON CONFLICT (id) DO UPDATE SET
+    title = EXCLUDED.title
+  , outline = EXCLUDED.outline
+  , content = EXCLUDED.content
+  , html = EXCLUDED.html
+  , tags = EXCLUDED.tags
+  , genre = EXCLUDED.genre
+  , created_at = EXCLUDED.created_at
+  , updated_at = EXCLUDED.updated_at
+  , created_by = EXCLUDED.created_by
+  , updated_by = EXCLUDED.updated_by
+  WHERE _overwrite
+  RETURNING doc.id, (doc.xmax = 0) AS inserted;
+$$ LANGUAGE SQL;
@@ -0,0 +1,19 @@
+-- Documents can be listed by genre and / or by tag. A NULL filter matches every
+-- document.
+DROP FUNCTION IF EXISTS api.list_documents(INTEGER, INTEGER);
+
+CREATE FUNCTION api.list_documents (
+  _limit INTEGER
+, _offset INTEGER
+, _genre main.GENRE DEFAULT NULL
+, _tag TEXT DEFAULT NULL
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  WHERE (_genre IS NULL OR genre = _genre)
+    AND (_tag IS NULL OR tags @> ARRAY[_tag])
+  ORDER BY created_at DESC
+  LIMIT _limit OFFSET _offset;
+$$ LANGUAGE SQL;
+
+CREATE INDEX IF NOT EXISTS documents_tags_idx ON main.documents USING GIN (tags);
@@ -0,0 +1,6 @@
+-- Number of documents of each genre, for monitoring.
+CREATE FUNCTION api.count_documents (
+) RETURNS TABLE (genre main.GENRE, count BIGINT)
+AS $$
+  SELECT genre, COUNT(*) FROM main.documents GROUP BY genre;
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,17 @@
+-- Returns the documents following the one created at _created_at with the id _id,
+-- oldest first. The documents are exported by following the key of the last document
+-- of each page, which, unlike an offset, is not shifted by concurrent writes. The first
+-- page is returned when the key is NULL.
+CREATE FUNCTION api.export_documents (
+  _limit INTEGER
+, _created_at TIMESTAMPTZ DEFAULT NULL
+, _id UUID DEFAULT NULL
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  WHERE _created_at IS NULL OR (created_at, id) > (_created_at, _id)
+  ORDER BY created_at, id
+  LIMIT _limit;
+$$ LANGUAGE SQL;
+
+CREATE INDEX IF NOT EXISTS documents_created_at_id_idx ON main.documents (created_at, id);
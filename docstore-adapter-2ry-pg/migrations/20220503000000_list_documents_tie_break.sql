@@ -0,0 +1,27 @@
+-- Documents created at the same time are listed in the order of their ids, so that
+-- consecutive pages neither repeat nor skip any of them.
+CREATE OR REPLACE FUNCTION api.list_documents (
+  _limit INTEGER
+, _offset INTEGER
+, _genre main.GENRE DEFAULT NULL
+, _tag TEXT DEFAULT NULL
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  WHERE (_genre IS NULL OR genre = _genre)
+    AND (_tag IS NULL OR tags @> ARRAY[_tag])
+  ORDER BY created_at DESC, id DESC
+  LIMIT _limit OFFSET _offset;
+$$ LANGUAGE SQL;
+
+CREATE OR REPLACE FUNCTION api.list_documents_by_author (
+  _author TEXT
+, _limit INTEGER
+, _offset INTEGER
+) RETURNS SETOF main.documents
+AS $$
+  SELECT * FROM main.documents
+  WHERE created_by = _author
+  ORDER BY created_at DESC, id DESC
+  LIMIT _limit OFFSET _offset;
+$$ LANGUAGE SQL;
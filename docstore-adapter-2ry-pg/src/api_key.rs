@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Row};
+use std::str::FromStr;
+use tracing::{instrument, Instrument};
+use uuid::Uuid;
+
+use super::Error as PostgresError;
+use super::{sql_span, PostgresqlStorage};
+use docstore_domain::model::api_key::ApiKey;
+use docstore_domain::model::authorization::Permission;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::error::Error;
+use docstore_domain::ports::secondary::api_key::ApiKeyStorage;
+
+// The entity received from sqlx, see DocumentEntity for the rationale.
+// The scopes are stored as text, unknown scopes are dropped.
+struct ApiKeyEntity {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl<'c> FromRow<'c, PgRow> for ApiKeyEntity {
+    fn from_row(row: &'c PgRow) -> Result<Self, sqlx::Error> {
+        Ok(ApiKeyEntity {
+            id: row.try_get(0)?,
+            name: row.try_get(1)?,
+            scopes: row.try_get(2)?,
+            created_at: row.try_get(3)?,
+            expires_at: row.try_get(4)?,
+            revoked_at: row.try_get(5)?,
+        })
+    }
+}
+
+impl From<ApiKeyEntity> for ApiKey {
+    fn from(entity: ApiKeyEntity) -> Self {
+        let ApiKeyEntity {
+            id,
+            name,
+            scopes,
+            created_at,
+            expires_at,
+            revoked_at,
+        } = entity;
+        ApiKey {
+            id,
+            name,
+            scopes: scopes
+                .iter()
+                .filter_map(|scope| Permission::from_str(scope).ok())
+                .collect(),
+            created_at,
+            expires_at,
+            revoked_at,
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyStorage for PostgresqlStorage {
+    #[instrument(skip(self, context, key, hash), fields(request_id = %context.request_id, id = %key.id))]
+    async fn add_api_key(
+        &self,
+        context: &RequestContext,
+        key: &ApiKey,
+        hash: &str,
+    ) -> Result<ApiKey, Error> {
+        let scopes = key
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>();
+        let mut tx = self.begin(context).await?;
+        let statement = r#"SELECT * FROM api.create_api_key($1::UUID, $2::TEXT, $3::TEXT, $4::TEXT[], $5::TIMESTAMPTZ, $6::TIMESTAMPTZ)"#;
+        let entity: ApiKeyEntity = sqlx::query_as(statement)
+            .bind(&key.id)
+            .bind(&key.name)
+            .bind(hash)
+            .bind(&scopes)
+            .bind(&key.created_at)
+            .bind(&key.expires_at)
+            .fetch_one(&mut tx)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        tx.commit().await.map_err(PostgresError::from)?;
+        Ok(ApiKey::from(entity))
+    }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn revoke_api_key(&self, context: &RequestContext, id: &Uuid) -> Result<ApiKey, Error> {
+        let mut tx = self.begin(context).await?;
+        let statement = r#"SELECT * FROM api.revoke_api_key($1::UUID)"#;
+        let entity: ApiKeyEntity = sqlx::query_as(statement)
+            .bind(id)
+            .fetch_one(&mut tx)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        tx.commit().await.map_err(PostgresError::from)?;
+        Ok(ApiKey::from(entity))
+    }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn list_api_keys(&self, context: &RequestContext) -> Result<Vec<ApiKey>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = r#"SELECT * FROM api.list_api_keys()"#;
+        let entities: Vec<ApiKeyEntity> = sqlx::query_as(statement)
+            .fetch_all(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+        Ok(entities.into_iter().map(ApiKey::from).collect())
+    }
+
+    #[instrument(skip(self, context, hash), fields(request_id = %context.request_id))]
+    async fn find_api_key(
+        &self,
+        context: &RequestContext,
+        hash: &str,
+    ) -> Result<Option<ApiKey>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = r#"SELECT * FROM api.find_api_key($1::TEXT)"#;
+        let entity: Option<ApiKeyEntity> = sqlx::query_as(statement)
+            .bind(hash)
+            .fetch_optional(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+        Ok(entity.map(ApiKey::from))
+    }
+}
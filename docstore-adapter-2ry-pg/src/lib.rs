@@ -1,13 +1,18 @@
+use docstore_domain::model::context::RequestContext;
 use docstore_domain::model::error::Error as ModelError;
 use docstore_domain::ports::secondary::remote::Error as RemoteError;
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 use snafu::Snafu;
-use sqlx::postgres::PgPool;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnection, PgPool, Postgres};
+use sqlx::Transaction;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use url::Url;
 
+pub mod api_key;
 pub mod remote;
 pub mod storage;
 pub mod utils;
@@ -32,6 +37,14 @@ pub enum Error {
     #[snafu(display("Operation violates model: {}", details))]
     ModelViolation { details: String },
 
+    /// The request's deadline was reached
+    #[snafu(display("Deadline exceeded"))]
+    DeadlineExceeded,
+
+    /// The schema could not be migrated
+    #[snafu(display("Migration: {}", source))]
+    Migration { source: sqlx::migrate::MigrateError },
+
     /// The requested operation violates the data model
     #[snafu(display("UnHandled Error: {}", source))]
     UnHandledError { source: sqlx::Error },
@@ -41,6 +54,10 @@ impl From<sqlx::Error> for Error {
     fn from(e: sqlx::Error) -> Self {
         match e {
             sqlx::Error::RowNotFound => Error::NotFound,
+            // 57014 is 'query_canceled', raised when the statement timeout is reached.
+            sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("57014") => {
+                Error::DeadlineExceeded
+            }
             sqlx::Error::Database(db_err) => Error::UnHandledError {
                 source: sqlx::Error::Database(db_err),
             },
@@ -51,14 +68,53 @@ impl From<sqlx::Error> for Error {
 
 impl From<Error> for ModelError {
     fn from(e: Error) -> Self {
-        ModelError::Storage {
-            source: Box::new(e),
+        match e {
+            Error::DeadlineExceeded => ModelError::DeadlineExceeded,
+            Error::NotFound => ModelError::NotFound,
+            _ => ModelError::Storage {
+                source: Box::new(e),
+            },
         }
     }
 }
-#[derive(Debug)]
+
+/// The changes made to the schema of the database image, in the 'migrations' directory.
+/// They are applied when the storage is created, and recorded in the
+/// '_sqlx_migrations' table, so that each one is only applied once.
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!();
+
+#[derive(Clone, Debug)]
 pub struct PostgresqlStorage {
     pub pool: Arc<PgPool>,
+    // Number of requests waiting for a connection.
+    waiting: Arc<AtomicUsize>,
+}
+
+/// The state of the connection pool.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStatus {
+    /// Number of connections, idle or in use.
+    pub size: u32,
+    pub idle: usize,
+    /// Number of requests waiting for a connection.
+    pub waiting: usize,
+}
+
+// Counts a request as waiting for a connection while it is alive, even if the request
+// is cancelled while it waits.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Waiting(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -70,10 +126,101 @@ pub struct PostgresqlStorageConfig {
 impl PostgresqlStorage {
     pub async fn new(config: &PostgresqlStorageConfig) -> Result<Self, Error> {
         let pool = remote::connection_pool(config).await.context(Connection)?;
+        MIGRATOR.run(&pool).await.context(Migration)?;
         Ok(PostgresqlStorage {
             pool: Arc::new(pool),
+            waiting: Arc::new(AtomicUsize::new(0)),
         })
     }
+
+    pub fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle(),
+            waiting: self.waiting.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Closes the connections of the pool, once they are released. The storage (and
+    /// its clones) cannot be used afterwards.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    // Starts a transaction in which the statements are bounded by the time left
+    // before the request's deadline.
+    pub(crate) async fn begin(
+        &self,
+        context: &RequestContext,
+    ) -> Result<Transaction<'static, Postgres>, Error> {
+        let mut tx = {
+            let _waiting = Waiting::new(&self.waiting);
+            self.pool.begin().await?
+        };
+        if let Some(millis) = remaining_millis(context)? {
+            // SET does not accept bind parameters, but millis is a number.
+            sqlx::query(&format!("SET LOCAL statement_timeout = {}", millis))
+                .execute(&mut tx)
+                .await?;
+        }
+        Ok(tx)
+    }
+
+    // Gets a connection for the statements which only read. They only need a
+    // transaction to be bounded by the request's deadline: the timeout cannot be set
+    // by the statement itself (eg. with set_config), it is armed when the statement
+    // starts. Without a deadline, the BEGIN and COMMIT round trips are saved.
+    pub(crate) async fn reader(&self, context: &RequestContext) -> Result<Reader, Error> {
+        if context.remaining().is_some() {
+            return Ok(Reader::Transaction(self.begin(context).await?));
+        }
+        let _waiting = Waiting::new(&self.waiting);
+        Ok(Reader::Connection(self.pool.acquire().await?))
+    }
+}
+
+// The time left before the request's deadline, in milliseconds, if it has one.
+fn remaining_millis(context: &RequestContext) -> Result<Option<u128>, Error> {
+    match context.remaining() {
+        Some(remaining) if remaining.as_millis() == 0 => Err(Error::DeadlineExceeded),
+        Some(remaining) => Ok(Some(remaining.as_millis())),
+        None => Ok(None),
+    }
+}
+
+/// The connection used by the statements which only read (see
+/// `PostgresqlStorage::reader`).
+pub(crate) enum Reader {
+    Connection(PoolConnection<Postgres>),
+    Transaction(Transaction<'static, Postgres>),
+}
+
+impl Reader {
+    pub(crate) fn connection(&mut self) -> &mut PgConnection {
+        match self {
+            Reader::Connection(connection) => connection,
+            Reader::Transaction(tx) => tx,
+        }
+    }
+
+    pub(crate) async fn finish(self) -> Result<(), Error> {
+        if let Reader::Transaction(tx) = self {
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+}
+
+// The span of a SQL statement, named and tagged following the OpenTelemetry conventions
+// for database clients, so that exporters show it as a call to PostgreSQL.
+pub(crate) fn sql_span(statement: &'static str) -> tracing::Span {
+    tracing::info_span!(
+        "sql",
+        otel.name = "postgresql",
+        otel.kind = "client",
+        db.system = "postgresql",
+        db.statement = statement,
+    )
 }
 
 impl Default for PostgresqlStorageConfig {
@@ -2,16 +2,23 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use sqlx::postgres::PgRow;
 use sqlx::{FromRow, Row};
+use std::collections::{HashMap, HashSet};
+use tracing::{instrument, Instrument};
 use uuid::Uuid;
 
 use super::Error as PostgresError;
-use super::PostgresqlStorage;
+use super::{sql_span, PostgresqlStorage};
+use docstore_domain::model::context::RequestContext;
 use docstore_domain::model::document::{
-    AddDocumentRequest, Document, Genre, GetDocumentRequest, ListDocumentsRequest,
+    ConflictPolicy, Document, ExportDocumentsRequest, Genre, GetDocumentRequest, ImportSummary,
+    ListDocumentsByAuthorRequest, ListDocumentsRequest,
 };
 use docstore_domain::model::error::Error;
 use docstore_domain::ports::secondary::storage::DocumentStorage;
 
+// Number of documents inserted by each statement of a bulk insert.
+const BULK_CHUNK_SIZE: usize = 500;
+
 #[derive(sqlx::Type)]
 #[sqlx(type_name = "genre")] // only for PostgreSQL to match a type definition
 #[sqlx(rename_all = "lowercase")]
@@ -23,6 +30,19 @@ enum GenreEntity {
     Tbd,
 }
 
+impl GenreEntity {
+    // The label of the value in the PostgreSQL enum.
+    fn as_str(&self) -> &'static str {
+        match self {
+            GenreEntity::Tutorial => "tutorial",
+            GenreEntity::Howto => "howto",
+            GenreEntity::Background => "background",
+            GenreEntity::Reference => "reference",
+            GenreEntity::Tbd => "tbd",
+        }
+    }
+}
+
 impl From<GenreEntity> for Genre {
     fn from(entity: GenreEntity) -> Genre {
         match entity {
@@ -62,6 +82,75 @@ struct DocumentEntity {
     pub genre: GenreEntity,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+}
+
+// The columns of a batch of documents, as expected by the functions inserting
+// documents with UNNEST. Since PostgreSQL arrays cannot be ragged, the tags of
+// each document are encoded as a JSON array.
+struct DocumentColumns {
+    ids: Vec<Uuid>,
+    titles: Vec<String>,
+    outlines: Vec<String>,
+    contents: Vec<String>,
+    htmls: Vec<String>,
+    tags: Vec<String>,
+    genres: Vec<String>,
+    created_at: Vec<DateTime<Utc>>,
+    updated_at: Vec<DateTime<Utc>>,
+    created_by: Vec<Option<String>>,
+    updated_by: Vec<Option<String>>,
+}
+
+impl From<&[Document]> for DocumentColumns {
+    fn from(documents: &[Document]) -> Self {
+        DocumentColumns {
+            ids: documents.iter().map(|document| document.id).collect(),
+            titles: documents
+                .iter()
+                .map(|document| document.title.clone())
+                .collect(),
+            outlines: documents
+                .iter()
+                .map(|document| document.outline.clone())
+                .collect(),
+            contents: documents
+                .iter()
+                .map(|document| document.content.clone())
+                .collect(),
+            htmls: documents
+                .iter()
+                .map(|document| document.html.clone())
+                .collect(),
+            tags: documents
+                .iter()
+                .map(|document| {
+                    serde_json::to_string(&document.tags).unwrap_or_else(|_| String::from("[]"))
+                })
+                .collect(),
+            genres: documents
+                .iter()
+                .map(|document| GenreEntity::from(&document.genre).as_str().to_string())
+                .collect(),
+            created_at: documents
+                .iter()
+                .map(|document| document.created_at)
+                .collect(),
+            updated_at: documents
+                .iter()
+                .map(|document| document.updated_at)
+                .collect(),
+            created_by: documents
+                .iter()
+                .map(|document| document.created_by.clone())
+                .collect(),
+            updated_by: documents
+                .iter()
+                .map(|document| document.updated_by.clone())
+                .collect(),
+        }
+    }
 }
 
 impl<'c> FromRow<'c, PgRow> for DocumentEntity {
@@ -76,6 +165,8 @@ impl<'c> FromRow<'c, PgRow> for DocumentEntity {
             genre: row.try_get(6)?,
             created_at: row.try_get(7)?,
             updated_at: row.try_get(8)?,
+            created_by: row.try_get(9)?,
+            updated_by: row.try_get(10)?,
         })
     }
 }
@@ -92,6 +183,8 @@ impl From<DocumentEntity> for Document {
             genre,
             created_at,
             updated_at,
+            created_by,
+            updated_by,
         } = entity;
         Document {
             id,
@@ -103,52 +196,262 @@ impl From<DocumentEntity> for Document {
             genre: Genre::from(genre),
             created_at,
             updated_at,
+            created_by,
+            updated_by,
         }
     }
 }
 
 #[async_trait]
 impl DocumentStorage for PostgresqlStorage {
-    async fn list_documents(&self, request: &ListDocumentsRequest) -> Result<Vec<Document>, Error> {
-        let entities: Vec<DocumentEntity> =
-            sqlx::query_as(r#"SELECT * FROM api.list_documents($1::INTEGER, $2::INTEGER)"#)
-                .bind(&request.limit)
-                .bind(&request.offset)
-                .fetch_all(&*self.pool)
-                .await
-                .map_err(PostgresError::from)?;
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn list_documents(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsRequest,
+    ) -> Result<Vec<Document>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = r#"SELECT * FROM api.list_documents($1::INTEGER, $2::INTEGER, $3::main.GENRE, $4::TEXT)"#;
+        let entities: Vec<DocumentEntity> = sqlx::query_as(statement)
+            .bind(&request.limit)
+            .bind(&request.offset)
+            .bind(request.genre.as_ref().map(GenreEntity::from))
+            .bind(&request.tag)
+            .fetch_all(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+
+        let documents = entities.into_iter().map(Document::from).collect::<Vec<_>>();
+
+        Ok(documents)
+    }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn list_documents_by_author(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsByAuthorRequest,
+    ) -> Result<Vec<Document>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement =
+            r#"SELECT * FROM api.list_documents_by_author($1::TEXT, $2::INTEGER, $3::INTEGER)"#;
+        let entities: Vec<DocumentEntity> = sqlx::query_as(statement)
+            .bind(&request.author)
+            .bind(&request.limit)
+            .bind(&request.offset)
+            .fetch_all(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
 
         let documents = entities.into_iter().map(Document::from).collect::<Vec<_>>();
 
         Ok(documents)
     }
 
-    async fn add_document(&self, request: &AddDocumentRequest) -> Result<Document, Error> {
-        let entity: DocumentEntity =
-            sqlx::query_as(r#"SELECT * FROM api.add_document($1::UUID, $2::TEXT, $3::TEXT, $4::TEXT, $5::TEXT, $6::TEXT[], $7::main.GENRE)"#)
-                .bind(&request.id)
-                .bind(&request.title)
-                .bind(&request.outline)
-                .bind(&request.content)
-                .bind(&request.html)
-                .bind(&request.tags)
-                .bind(GenreEntity::from(&request.genre))
-                .fetch_one(&*self.pool)
+    #[instrument(skip(self, context, document), fields(request_id = %context.request_id, id = %document.id))]
+    async fn add_document(
+        &self,
+        context: &RequestContext,
+        document: &Document,
+    ) -> Result<Document, Error> {
+        let mut tx = self.begin(context).await?;
+        let statement = r#"SELECT * FROM api.add_document($1::UUID, $2::TEXT, $3::TEXT, $4::TEXT, $5::TEXT, $6::TEXT[], $7::main.GENRE, $8::TIMESTAMPTZ, $9::TIMESTAMPTZ, $10::TEXT, $11::TEXT)"#;
+        let entity: DocumentEntity = sqlx::query_as(statement)
+            .bind(&document.id)
+            .bind(&document.title)
+            .bind(&document.outline)
+            .bind(&document.content)
+            .bind(&document.html)
+            .bind(&document.tags)
+            .bind(GenreEntity::from(&document.genre))
+            .bind(&document.created_at)
+            .bind(&document.updated_at)
+            .bind(&document.created_by)
+            .bind(&document.updated_by)
+            .fetch_one(&mut tx)
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        tx.commit().await.map_err(PostgresError::from)?;
+        Ok(Document::from(entity))
+    }
+
+    #[instrument(skip(self, context, documents), fields(request_id = %context.request_id, count = documents.len()))]
+    async fn bulk_add_documents(
+        &self,
+        context: &RequestContext,
+        documents: &[Document],
+        atomic: bool,
+    ) -> Result<Vec<Option<Document>>, Error> {
+        let mut tx = self.begin(context).await?;
+        let mut stored = HashMap::new();
+        for chunk in documents.chunks(BULK_CHUNK_SIZE) {
+            let columns = DocumentColumns::from(chunk);
+            let statement = r#"SELECT * FROM api.add_documents($1::UUID[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TIMESTAMPTZ[], $9::TIMESTAMPTZ[], $10::TEXT[], $11::TEXT[])"#;
+            let entities: Vec<DocumentEntity> = sqlx::query_as(statement)
+                .bind(columns.ids)
+                .bind(columns.titles)
+                .bind(columns.outlines)
+                .bind(columns.contents)
+                .bind(columns.htmls)
+                .bind(columns.tags)
+                .bind(columns.genres)
+                .bind(columns.created_at)
+                .bind(columns.updated_at)
+                .bind(columns.created_by)
+                .bind(columns.updated_by)
+                .fetch_all(&mut tx)
+                .instrument(sql_span(statement))
                 .await
                 .map_err(PostgresError::from)?;
-        Ok(Document::from(entity))
+            stored.extend(
+                entities
+                    .into_iter()
+                    .map(|entity| (entity.id, Document::from(entity))),
+            );
+        }
+
+        let documents = documents
+            .iter()
+            .map(|document| stored.remove(&document.id))
+            .collect::<Vec<_>>();
+        if atomic && documents.iter().any(Option::is_none) {
+            tx.rollback().await.map_err(PostgresError::from)?;
+        } else {
+            tx.commit().await.map_err(PostgresError::from)?;
+        }
+        Ok(documents)
     }
 
-    async fn get_document(&self, request: &GetDocumentRequest) -> Result<Document, Error> {
-        let entity: DocumentEntity =
-            sqlx::query_as(r#"SELECT * FROM api.get_document_by_id($1::UUID)"#)
-                .bind(&request.id)
-                .fetch_one(&*self.pool)
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn export_documents(
+        &self,
+        context: &RequestContext,
+        request: &ExportDocumentsRequest,
+    ) -> Result<Vec<Document>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement =
+            r#"SELECT * FROM api.export_documents($1::INTEGER, $2::TIMESTAMPTZ, $3::UUID)"#;
+        let entities: Vec<DocumentEntity> = sqlx::query_as(statement)
+            .bind(&request.limit)
+            .bind(request.after.as_ref().map(|key| key.created_at))
+            .bind(request.after.as_ref().map(|key| key.id))
+            .fetch_all(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+
+        let documents = entities.into_iter().map(Document::from).collect::<Vec<_>>();
+
+        Ok(documents)
+    }
+
+    #[instrument(skip(self, context, documents), fields(request_id = %context.request_id, count = documents.len()))]
+    async fn import_documents(
+        &self,
+        context: &RequestContext,
+        documents: &[Document],
+        on_conflict: ConflictPolicy,
+    ) -> Result<ImportSummary, Error> {
+        let mut tx = self.begin(context).await?;
+        let mut summary = ImportSummary::default();
+        let mut stored = HashSet::new();
+        for chunk in documents.chunks(BULK_CHUNK_SIZE) {
+            let columns = DocumentColumns::from(chunk);
+            let statement = r#"SELECT * FROM api.import_documents($1::UUID[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TIMESTAMPTZ[], $9::TIMESTAMPTZ[], $10::TEXT[], $11::TEXT[], $12::BOOLEAN)"#;
+            let rows: Vec<(Uuid, bool)> = sqlx::query_as(statement)
+                .bind(columns.ids)
+                .bind(columns.titles)
+                .bind(columns.outlines)
+                .bind(columns.contents)
+                .bind(columns.htmls)
+                .bind(columns.tags)
+                .bind(columns.genres)
+                .bind(columns.created_at)
+                .bind(columns.updated_at)
+                .bind(columns.created_by)
+                .bind(columns.updated_by)
+                .bind(on_conflict == ConflictPolicy::Overwrite)
+                .fetch_all(&mut tx)
+                .instrument(sql_span(statement))
                 .await
                 .map_err(PostgresError::from)?;
+            for (id, inserted) in rows {
+                if inserted {
+                    summary.created += 1;
+                } else {
+                    summary.overwritten += 1;
+                }
+                stored.insert(id);
+            }
+        }
+        summary.skipped = documents.len() - summary.created - summary.overwritten;
+
+        if on_conflict == ConflictPolicy::Fail && summary.skipped > 0 {
+            tx.rollback().await.map_err(PostgresError::from)?;
+            let ids = documents
+                .iter()
+                .map(|document| document.id)
+                .filter(|id| !stored.contains(id))
+                .collect();
+            return Err(Error::Conflict { ids });
+        }
+        tx.commit().await.map_err(PostgresError::from)?;
+        Ok(summary)
+    }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn get_document(
+        &self,
+        context: &RequestContext,
+        request: &GetDocumentRequest,
+    ) -> Result<Document, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = r#"SELECT * FROM api.get_document_by_id($1::UUID)"#;
+        let entity: DocumentEntity = sqlx::query_as(statement)
+            .bind(&request.id)
+            .fetch_one(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
 
         let document = Document::from(entity);
 
         Ok(document)
     }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn count_documents(&self, context: &RequestContext) -> Result<Vec<(Genre, u64)>, Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = r#"SELECT * FROM api.count_documents()"#;
+        let counts: Vec<(GenreEntity, i64)> = sqlx::query_as(statement)
+            .fetch_all(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+        Ok(counts
+            .into_iter()
+            .map(|(genre, count)| (Genre::from(genre), count as u64))
+            .collect())
+    }
+
+    #[instrument(skip(self, context), fields(request_id = %context.request_id))]
+    async fn health_check(&self, context: &RequestContext) -> Result<(), Error> {
+        let mut reader = self.reader(context).await?;
+        let statement = "SELECT 1";
+        sqlx::query(statement)
+            .execute(reader.connection())
+            .instrument(sql_span(statement))
+            .await
+            .map_err(PostgresError::from)?;
+        reader.finish().await?;
+        Ok(())
+    }
 }
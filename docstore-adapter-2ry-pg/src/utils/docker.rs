@@ -50,12 +50,15 @@ pub async fn initialize_with_param(cleanup: bool) -> Result<(), Error> {
         });
     }
     let config = PostgresqlStorageConfig::default_testing();
-    let _client = remote::connection_pool(&config)
+    let pool = remote::connection_pool(&config)
         .await
-        .context(PostgresqlPoolConnectionFailed)?
-        .acquire()
+        .context(PostgresqlPoolConnectionFailed)?;
+    // The tests use the schema of the storage, not only the one of the image.
+    crate::MIGRATOR
+        .run(&pool)
         .await
-        .context(PostgresqlConnectionFailed)?;
+        .context(PostgresqlMigrationFailed)?;
+    let _client = pool.acquire().await.context(PostgresqlConnectionFailed)?;
 
     Ok(())
 }
@@ -71,6 +74,9 @@ pub enum Error {
     #[snafu(display("Connection to postgresql: {}", source))]
     PostgresqlConnectionFailed { source: sqlx::Error },
 
+    #[snafu(display("Migration of postgresql: {}", source))]
+    PostgresqlMigrationFailed { source: sqlx::migrate::MigrateError },
+
     #[snafu(display("docker version: {}", source))]
     Version { source: BollardError },
 
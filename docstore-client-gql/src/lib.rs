@@ -38,10 +38,6 @@ pub async fn list_documents(
     };
     let variables = list_documents::Variables { request };
     let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        "Accept-Encoding",
-        reqwest::header::HeaderValue::from_static("gzip, deflate, br"),
-    );
     headers.insert(
         "Content-Type",
         reqwest::header::HeaderValue::from_static("application/json"),
@@ -75,6 +71,7 @@ pub async fn list_documents(
             genre: o.genre,
             created_at: o.created_at,
             updated_at: o.updated_at,
+            author: o.author,
         })
         .collect();
     Ok(documents)
@@ -96,10 +93,6 @@ pub async fn get_document(
     let request = get_document::GetDocumentRequest { id: id.into() };
     let variables = get_document::Variables { request };
     let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        "Accept-Encoding",
-        reqwest::header::HeaderValue::from_static("gzip, deflate, br"),
-    );
     headers.insert(
         "Content-Type",
         reqwest::header::HeaderValue::from_static("application/json"),
@@ -130,6 +123,7 @@ pub async fn get_document(
         genre: doc.genre,
         created_at: doc.created_at,
         updated_at: doc.updated_at,
+        author: doc.author,
     })
 }
 
@@ -152,6 +146,7 @@ impl From<add_document::AddDocumentAddDocument> for DocumentResponse {
             genre: add_document.genre,
             created_at: add_document.created_at,
             updated_at: add_document.updated_at,
+            author: add_document.author,
         }
     }
 }
@@ -181,10 +176,6 @@ pub async fn add_document(
     };
     let variables = add_document::Variables { request };
     let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        "Accept-Encoding",
-        reqwest::header::HeaderValue::from_static("gzip, deflate, br"),
-    );
     headers.insert(
         "Content-Type",
         reqwest::header::HeaderValue::from_static("application/json"),
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::model::error::Error;
+use crate::ports::secondary::authorization::AuthorizationPolicy;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Permission {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Permission, Self::Err> {
+        match input {
+            "read" => Ok(Permission::Read),
+            "write" => Ok(Permission::Write),
+            "admin" => Ok(Permission::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The caller on whose behalf a request is made.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Principal {
+    /// The caller's identity, None for anonymous callers.
+    pub subject: Option<String>,
+    pub roles: Vec<String>,
+    /// When present, the principal is only granted these permissions, regardless of
+    /// its roles. This is used for API keys.
+    pub scopes: Option<Vec<Permission>>,
+}
+
+impl Principal {
+    pub fn anonymous() -> Self {
+        Principal::default()
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.subject.is_none()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RolePolicyConfig {
+    /// Permissions granted to each role.
+    pub roles: HashMap<String, Vec<Permission>>,
+    /// Roles given to anonymous callers.
+    #[serde(default)]
+    pub anonymous_roles: Vec<String>,
+}
+
+/// An authorization policy based on a role -> permissions matrix.
+#[derive(Clone, Debug)]
+pub struct RolePolicy {
+    config: RolePolicyConfig,
+}
+
+impl RolePolicy {
+    pub fn new(config: RolePolicyConfig) -> Self {
+        RolePolicy { config }
+    }
+}
+
+impl AuthorizationPolicy for RolePolicy {
+    fn authorize(&self, principal: &Principal, permission: Permission) -> Result<(), Error> {
+        let granted = match &principal.scopes {
+            Some(scopes) => scopes.contains(&permission),
+            None => {
+                let roles = if principal.is_anonymous() {
+                    &self.config.anonymous_roles
+                } else {
+                    &principal.roles
+                };
+                roles.iter().any(|role| {
+                    self.config
+                        .roles
+                        .get(role)
+                        .map(|permissions| permissions.contains(&permission))
+                        .unwrap_or(false)
+                })
+            }
+        };
+        if granted {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied {
+                subject: principal
+                    .subject
+                    .clone()
+                    .unwrap_or_else(|| String::from("anonymous")),
+                permission,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RolePolicy {
+        let mut roles = HashMap::new();
+        roles.insert(String::from("reader"), vec![Permission::Read]);
+        roles.insert(
+            String::from("editor"),
+            vec![Permission::Read, Permission::Write],
+        );
+        RolePolicy::new(RolePolicyConfig {
+            roles,
+            anonymous_roles: vec![String::from("reader")],
+        })
+    }
+
+    #[test]
+    fn should_grant_permissions_of_principal_roles() {
+        let principal = Principal {
+            subject: Some(String::from("bob")),
+            roles: vec![String::from("editor")],
+            scopes: None,
+        };
+        assert!(policy().authorize(&principal, Permission::Write).is_ok());
+        assert!(policy().authorize(&principal, Permission::Admin).is_err());
+    }
+
+    #[test]
+    fn should_only_grant_scopes_when_present() {
+        let principal = Principal {
+            subject: Some(String::from("api-key:importer")),
+            roles: vec![String::from("editor")],
+            scopes: Some(vec![Permission::Read]),
+        };
+        assert!(policy().authorize(&principal, Permission::Read).is_ok());
+        assert!(policy().authorize(&principal, Permission::Write).is_err());
+    }
+
+    #[test]
+    fn should_use_anonymous_roles_for_anonymous_principal() {
+        let principal = Principal::anonymous();
+        assert!(policy().authorize(&principal, Permission::Read).is_ok());
+        assert!(policy().authorize(&principal, Permission::Write).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::model::authorization::Principal;
+
+/// Information about the request, carried through every port call, so that adapters
+/// can use it (eg. for tracing or for timeouts).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RequestContext {
+    pub request_id: Uuid,
+    pub principal: Principal,
+    /// The request must be completed before that time.
+    pub deadline: Option<DateTime<Utc>>,
+    /// The caller's preferred locale, eg 'en-US'.
+    pub locale: Option<String>,
+}
+
+impl RequestContext {
+    pub fn new(principal: Principal) -> Self {
+        RequestContext {
+            request_id: Uuid::new_v4(),
+            principal,
+            deadline: None,
+            locale: None,
+        }
+    }
+
+    /// Returns the time left until the deadline, which is zero if the deadline is past.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| {
+            (deadline - Utc::now())
+                .to_std()
+                .unwrap_or_else(|_| Duration::from_secs(0))
+        })
+    }
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        RequestContext::new(Principal::anonymous())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_have_no_time_remaining_after_deadline() {
+        let mut context = RequestContext::default();
+        assert_eq!(context.remaining(), None);
+        context.deadline = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert_eq!(context.remaining(), Some(Duration::from_secs(0)));
+        context.deadline = Some(Utc::now() + chrono::Duration::seconds(60));
+        assert!(context.remaining().unwrap() > Duration::from_secs(30));
+    }
+}
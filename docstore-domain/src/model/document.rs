@@ -2,7 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::model::validation::FieldError;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Genre {
     Tutorial,
     Howto,
@@ -41,7 +43,7 @@ impl std::str::FromStr for Genre {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Document {
     pub id: Uuid,
     pub title: String,
@@ -54,17 +56,66 @@ pub struct Document {
     pub genre: Genre,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Subject of the principal who created the document.
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// Subject of the principal who last updated the document.
+    #[serde(default)]
+    pub updated_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ListDocumentsRequest {
     pub offset: u32,
     pub limit: u32,
+    /// Only list the documents of this genre.
+    #[serde(default)]
+    pub genre: Option<Genre>,
+    /// Only list the documents with this tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Where an export resumes: documents are exported in the order of their creation, and
+/// the id breaks the ties between documents created at the same time.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DocumentKey {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl From<&Document> for DocumentKey {
+    fn from(document: &Document) -> Self {
+        DocumentKey {
+            created_at: document.created_at,
+            id: document.id,
+        }
+    }
+}
+
+/// Reads every document, page by page. Unlike an offset, the key of the last exported
+/// document still points at the right place when documents are added or deleted
+/// during the export.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportDocumentsRequest {
+    /// The key of the last document of the previous page, None for the first page.
+    #[serde(default)]
+    pub after: Option<DocumentKey>,
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListDocumentsByAuthorRequest {
+    pub author: String,
+    pub offset: u32,
+    pub limit: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AddDocumentRequest {
-    pub id: Uuid,
+    /// The id of the new document, generated if it is not given.
+    #[serde(default)]
+    pub id: Option<Uuid>,
     pub title: String,
     #[serde(rename = "abstract")]
     pub outline: String,
@@ -75,6 +126,72 @@ pub struct AddDocumentRequest {
     pub genre: Genre,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BulkAddDocumentsRequest {
+    pub documents: Vec<AddDocumentRequest>,
+    /// When set, either every document is added, or none is.
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// What happened to one of the documents of a `BulkAddDocumentsRequest`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BulkAddOutcome {
+    Created {
+        document: Document,
+    },
+    /// A document with the same id already exists, or appears earlier in the request.
+    Conflict {
+        id: Uuid,
+    },
+    Invalid {
+        errors: Vec<FieldError>,
+    },
+    /// The document is valid, but was not added because the atomic request failed.
+    Aborted,
+}
+
+/// What to do when an imported document already exists.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing document.
+    Skip,
+    /// Replace the existing document with the imported one.
+    Overwrite,
+    /// Abort the import.
+    Fail,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ConflictPolicy, Self::Err> {
+        match input {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "fail" => Ok(ConflictPolicy::Fail),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Restores documents as they are, including their timestamps and authors. Each id
+/// appears at most once.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportDocumentsRequest {
+    pub documents: Vec<Document>,
+    pub on_conflict: ConflictPolicy,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetDocumentRequest {
     pub id: Uuid,
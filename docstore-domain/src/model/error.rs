@@ -1,7 +1,94 @@
 use snafu::Snafu;
+use uuid::Uuid;
+
+use crate::model::authorization::Permission;
+use crate::model::validation::FieldError;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Storage Error: {}", source))]
     Storage { source: Box<dyn std::error::Error> },
+
+    #[snafu(display("Permission Denied: {} is not granted '{}'", subject, permission))]
+    PermissionDenied {
+        subject: String,
+        permission: Permission,
+    },
+
+    #[snafu(display("Not Found"))]
+    NotFound,
+
+    #[snafu(display("Invalid API Key"))]
+    InvalidApiKey,
+
+    #[snafu(display("Deadline Exceeded"))]
+    DeadlineExceeded,
+
+    #[snafu(display("Conflict: {} document(s) already exist", ids.len()))]
+    Conflict { ids: Vec<Uuid> },
+
+    #[snafu(display("Validation Failed: {}", describe(errors)))]
+    Validation { errors: Vec<FieldError> },
+}
+
+/// What an error means for the caller. Each primary adapter maps it to its own status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    PermissionDenied,
+    Unauthenticated,
+    NotFound,
+    Conflict,
+    InvalidArgument,
+    DeadlineExceeded,
+    /// The service failed, the caller is not at fault.
+    Internal,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Error::InvalidApiKey => ErrorKind::Unauthenticated,
+            Error::NotFound => ErrorKind::NotFound,
+            Error::Conflict { .. } => ErrorKind::Conflict,
+            Error::Validation { .. } => ErrorKind::InvalidArgument,
+            Error::DeadlineExceeded => ErrorKind::DeadlineExceeded,
+            Error::Storage { .. } => ErrorKind::Internal,
+        }
+    }
+
+    /// The message which can be returned to the caller. Storage errors may reveal
+    /// details of the database, so their message is generic: adapters log them instead.
+    pub fn public_message(&self) -> String {
+        match self.kind() {
+            ErrorKind::Internal => String::from("Internal Server Error"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_hide_the_message_of_internal_errors() {
+        let err = Error::Storage {
+            source: "relation \"main.documents\" does not exist".into(),
+        };
+        assert_eq!(err.kind(), ErrorKind::Internal);
+        assert_eq!(err.public_message(), "Internal Server Error");
+
+        let err = Error::NotFound;
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.public_message(), "Not Found");
+    }
 }
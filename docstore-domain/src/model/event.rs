@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::document::Document;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentEventKind {
+    Created,
+}
+
+/// A change made to a document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DocumentEvent {
+    pub id: Uuid,
+    pub kind: DocumentEventKind,
+    /// The document, as it is after the change.
+    pub document: Document,
+    pub occurred_at: DateTime<Utc>,
+    /// Subject of the principal who made the change.
+    pub actor: Option<String>,
+}
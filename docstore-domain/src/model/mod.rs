@@ -1,2 +1,7 @@
+pub mod api_key;
+pub mod authorization;
+pub mod context;
 pub mod document;
 pub mod error;
+pub mod event;
+pub mod validation;
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::model::document::AddDocumentRequest;
+
+/// Bounds checked on every request before it reaches the storage.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValidationConfig {
+    /// Maximum number of characters in a title.
+    pub max_title_length: usize,
+    /// Maximum number of characters in an abstract.
+    pub max_abstract_length: usize,
+    /// Maximum size of the content, and of the html, in bytes.
+    pub max_content_size: usize,
+    /// Maximum number of tags, after deduplication.
+    pub max_tags: usize,
+    /// Maximum number of characters in a tag.
+    pub max_tag_length: usize,
+    /// Maximum number of documents in a bulk request.
+    pub max_batch_size: usize,
+    /// Maximum size of the contents and htmls of all the documents of a bulk request,
+    /// in bytes.
+    pub max_batch_content_size: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            max_title_length: 200,
+            max_abstract_length: 2000,
+            max_content_size: 256 * 1024,
+            max_tags: 20,
+            max_tag_length: 50,
+            max_batch_size: 1000,
+            max_batch_content_size: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// A field of the request which did not pass the validation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FieldError {
+    /// Name of the field, as it appears in the API (eg. 'abstract').
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Checks the request, and returns a normalized copy of it: the title is trimmed, and
+/// the tags are trimmed, lowercased, and deduplicated. All the invalid fields are
+/// reported at once.
+pub fn validate_add_document(
+    request: &AddDocumentRequest,
+    config: &ValidationConfig,
+) -> Result<AddDocumentRequest, Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    let title = request.title.trim().to_string();
+    if title.is_empty() {
+        errors.push(FieldError::new("title", "must not be empty"));
+    } else if title.chars().count() > config.max_title_length {
+        errors.push(FieldError::new(
+            "title",
+            format!("must be at most {} characters", config.max_title_length),
+        ));
+    }
+    if title.chars().any(char::is_control) {
+        errors.push(FieldError::new(
+            "title",
+            "must not contain control characters",
+        ));
+    }
+
+    if request.outline.chars().count() > config.max_abstract_length {
+        errors.push(FieldError::new(
+            "abstract",
+            format!("must be at most {} characters", config.max_abstract_length),
+        ));
+    }
+
+    for (field, value) in [("content", &request.content), ("html", &request.html)] {
+        if value.len() > config.max_content_size {
+            errors.push(FieldError::new(
+                field,
+                format!("must be at most {} bytes", config.max_content_size),
+            ));
+        }
+        // PostgreSQL rejects NUL characters in text values.
+        if value.contains('\0') {
+            errors.push(FieldError::new(field, "must not contain NUL characters"));
+        }
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in &request.tags {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() || tags.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > config.max_tag_length {
+            errors.push(FieldError::new(
+                "tags",
+                format!(
+                    "'{}' must be at most {} characters",
+                    tag, config.max_tag_length
+                ),
+            ));
+        } else if !tag.chars().all(is_tag_char) {
+            errors.push(FieldError::new(
+                "tags",
+                format!(
+                    "'{}' must only contain letters, digits, '-', '_', '.', and '+'",
+                    tag
+                ),
+            ));
+        }
+        tags.push(tag);
+    }
+    if tags.len() > config.max_tags {
+        errors.push(FieldError::new(
+            "tags",
+            format!("must contain at most {} tags", config.max_tags),
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(AddDocumentRequest {
+        id: request.id,
+        title,
+        outline: request.outline.clone(),
+        content: request.content.clone(),
+        html: request.html.clone(),
+        tags,
+        genre: request.genre.clone(),
+    })
+}
+
+/// Tags are compared case insensitively, and inner whitespaces are replaced by '-',
+/// so that 'Rust  Async' and 'rust-async' are the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '+')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::document::Genre;
+
+    fn request(title: &str, tags: &[&str]) -> AddDocumentRequest {
+        AddDocumentRequest {
+            id: None,
+            title: title.to_string(),
+            outline: String::from("abstract"),
+            content: String::from("content"),
+            html: String::from("<p>content</p>"),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            genre: Genre::Tutorial,
+        }
+    }
+
+    #[test]
+    fn should_normalize_and_deduplicate_tags() {
+        let request = request(
+            "  Getting Started ",
+            &["Rust", " rust ", "Rust  Async", "", "rust-async"],
+        );
+        let request = validate_add_document(&request, &ValidationConfig::default()).unwrap();
+        assert_eq!(request.title, "Getting Started");
+        assert_eq!(request.tags, vec!["rust", "rust-async"]);
+    }
+
+    #[test]
+    fn should_report_every_invalid_field() {
+        let mut request = request("   ", &["c#"]);
+        request.content = "x".repeat(11);
+        let config = ValidationConfig {
+            max_content_size: 10,
+            ..ValidationConfig::default()
+        };
+        let errors = validate_add_document(&request, &config).unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, vec!["title", "content", "tags"]);
+    }
+}
@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+
+use crate::model::api_key::{ApiKey, CreateApiKeyRequest, CreatedApiKey, RevokeApiKeyRequest};
+use crate::model::authorization::Principal;
+use crate::model::context::RequestContext;
+use crate::model::error::Error;
+
+#[async_trait]
+pub trait ApiKeyManagement {
+    async fn create_api_key(
+        &self,
+        context: &RequestContext,
+        request: &CreateApiKeyRequest,
+    ) -> Result<CreatedApiKey, Error>;
+    async fn revoke_api_key(
+        &self,
+        context: &RequestContext,
+        request: &RevokeApiKeyRequest,
+    ) -> Result<ApiKey, Error>;
+    async fn list_api_keys(&self, context: &RequestContext) -> Result<Vec<ApiKey>, Error>;
+    /// Returns the principal identified by the secret, which must belong to an active key.
+    /// The context's principal is ignored, since the caller is not authenticated yet.
+    async fn authenticate_api_key(
+        &self,
+        context: &RequestContext,
+        secret: &str,
+    ) -> Result<Principal, Error>;
+}
@@ -1,29 +1,50 @@
 use async_trait::async_trait;
 
+use crate::model::context::RequestContext;
 use crate::model::document::{
-    AddDocumentRequest, Document, GetDocumentRequest, ListDocumentsRequest,
+    AddDocumentRequest, BulkAddDocumentsRequest, BulkAddOutcome, Document, ExportDocumentsRequest,
+    GetDocumentRequest, ImportDocumentsRequest, ImportSummary, ListDocumentsByAuthorRequest,
+    ListDocumentsRequest,
 };
 use crate::model::error::Error;
 
+#[mockall::automock]
 #[async_trait]
 pub trait DocumentStorage {
-    async fn list_documents(&self, request: &ListDocumentsRequest) -> Result<Vec<Document>, Error>;
-    async fn add_document(&self, request: &AddDocumentRequest) -> Result<Document, Error>;
-    async fn get_document(&self, request: &GetDocumentRequest) -> Result<Document, Error>;
-}
-
-#[async_trait]
-impl<T> DocumentStorage for T
-where
-    T: crate::ports::secondary::storage::DocumentStorage + Send + Sync,
-{
-    async fn list_documents(&self, request: &ListDocumentsRequest) -> Result<Vec<Document>, Error> {
-        self.list_documents(request).await
-    }
-    async fn add_document(&self, request: &AddDocumentRequest) -> Result<Document, Error> {
-        self.add_document(request).await
-    }
-    async fn get_document(&self, request: &GetDocumentRequest) -> Result<Document, Error> {
-        self.get_document(request).await
-    }
+    async fn list_documents(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsRequest,
+    ) -> Result<Vec<Document>, Error>;
+    async fn list_documents_by_author(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsByAuthorRequest,
+    ) -> Result<Vec<Document>, Error>;
+    async fn export_documents(
+        &self,
+        context: &RequestContext,
+        request: &ExportDocumentsRequest,
+    ) -> Result<Vec<Document>, Error>;
+    async fn add_document(
+        &self,
+        context: &RequestContext,
+        request: &AddDocumentRequest,
+    ) -> Result<Document, Error>;
+    /// Returns the outcome of each document, in the order of the request.
+    async fn bulk_add_documents(
+        &self,
+        context: &RequestContext,
+        request: &BulkAddDocumentsRequest,
+    ) -> Result<Vec<BulkAddOutcome>, Error>;
+    async fn import_documents(
+        &self,
+        context: &RequestContext,
+        request: &ImportDocumentsRequest,
+    ) -> Result<ImportSummary, Error>;
+    async fn get_document(
+        &self,
+        context: &RequestContext,
+        request: &GetDocumentRequest,
+    ) -> Result<Document, Error>;
 }
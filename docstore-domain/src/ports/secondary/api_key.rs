@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::model::api_key::ApiKey;
+use crate::model::context::RequestContext;
+use crate::model::error::Error;
+
+#[mockall::automock]
+#[async_trait]
+pub trait ApiKeyStorage {
+    async fn add_api_key(
+        &self,
+        context: &RequestContext,
+        key: &ApiKey,
+        hash: &str,
+    ) -> Result<ApiKey, Error>;
+    async fn revoke_api_key(&self, context: &RequestContext, id: &Uuid) -> Result<ApiKey, Error>;
+    async fn list_api_keys(&self, context: &RequestContext) -> Result<Vec<ApiKey>, Error>;
+    async fn find_api_key(
+        &self,
+        context: &RequestContext,
+        hash: &str,
+    ) -> Result<Option<ApiKey>, Error>;
+}
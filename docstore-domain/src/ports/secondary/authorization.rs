@@ -0,0 +1,8 @@
+use crate::model::authorization::{Permission, Principal};
+use crate::model::error::Error;
+
+#[mockall::automock]
+pub trait AuthorizationPolicy {
+    /// Returns an error if the principal is not granted the permission.
+    fn authorize(&self, principal: &Principal, permission: Permission) -> Result<(), Error>;
+}
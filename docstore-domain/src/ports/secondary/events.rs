@@ -0,0 +1,9 @@
+use crate::model::event::DocumentEvent;
+
+/// Notifies other parts of the system of changes to documents.
+#[mockall::automock]
+pub trait EventPublisher {
+    /// Publishing must not block: the change is already stored when the event is
+    /// published, so implementations should drop events rather than wait.
+    fn publish(&self, event: DocumentEvent);
+}
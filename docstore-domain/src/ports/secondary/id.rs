@@ -0,0 +1,51 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use uuid::Uuid;
+
+/// The source of the identifiers given to new entities.
+#[mockall::automock]
+pub trait IdGenerator {
+    fn generate(&self) -> Uuid;
+}
+
+/// Generates random (v4) identifiers.
+#[derive(Clone, Debug, Default)]
+pub struct RandomIdGenerator;
+
+impl IdGenerator for RandomIdGenerator {
+    fn generate(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// Generates the identifiers 1, 2, 3, ... (as in `Uuid::from_u128`), for tests.
+#[derive(Debug, Default)]
+pub struct SequentialIdGenerator {
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    /// The first generated identifier is `Uuid::from_u128(first)`.
+    pub fn starting_at(first: u64) -> Self {
+        SequentialIdGenerator {
+            next: AtomicU64::new(first),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn generate(&self) -> Uuid {
+        Uuid::from_u128(u128::from(self.next.fetch_add(1, Ordering::SeqCst)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_generate_sequential_ids() {
+        let ids = SequentialIdGenerator::starting_at(1);
+        assert_eq!(ids.generate(), Uuid::from_u128(1));
+        assert_eq!(ids.generate(), Uuid::from_u128(2));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod api_key;
+pub mod authorization;
+pub mod clock;
+pub mod events;
+pub mod id;
 pub mod remote;
 pub mod storage;
@@ -1,14 +1,65 @@
 use async_trait::async_trait;
 
+use crate::model::context::RequestContext;
 use crate::model::document::{
-    AddDocumentRequest, Document, GetDocumentRequest, ListDocumentsRequest,
+    ConflictPolicy, Document, ExportDocumentsRequest, Genre, GetDocumentRequest, ImportSummary,
+    ListDocumentsByAuthorRequest, ListDocumentsRequest,
 };
 use crate::model::error::Error;
 
 #[mockall::automock]
 #[async_trait]
 pub trait DocumentStorage {
-    async fn list_documents(&self, request: &ListDocumentsRequest) -> Result<Vec<Document>, Error>;
-    async fn add_document(&self, document: &AddDocumentRequest) -> Result<Document, Error>;
-    async fn get_document(&self, document: &GetDocumentRequest) -> Result<Document, Error>;
+    async fn list_documents(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsRequest,
+    ) -> Result<Vec<Document>, Error>;
+    async fn list_documents_by_author(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsByAuthorRequest,
+    ) -> Result<Vec<Document>, Error>;
+    /// Returns at most `request.limit` documents following `request.after`, ordered by
+    /// creation time and then by id.
+    async fn export_documents(
+        &self,
+        context: &RequestContext,
+        request: &ExportDocumentsRequest,
+    ) -> Result<Vec<Document>, Error>;
+    /// Stores the document as it is: its id, timestamps, and authors are supplied by
+    /// the domain.
+    async fn add_document(
+        &self,
+        context: &RequestContext,
+        document: &Document,
+    ) -> Result<Document, Error>;
+    /// Stores the documents which do not conflict with an existing one, and returns, for
+    /// each document, the stored document, or None if there is a conflict. When `atomic`
+    /// is set and there is a conflict, nothing is stored.
+    async fn bulk_add_documents(
+        &self,
+        context: &RequestContext,
+        documents: &[Document],
+        atomic: bool,
+    ) -> Result<Vec<Option<Document>>, Error>;
+    /// Stores the documents as they are, in a single transaction. The ids of the documents
+    /// are unique. With `ConflictPolicy::Fail`, nothing is stored if one of the documents
+    /// already exists, and the error is `Error::Conflict`.
+    async fn import_documents(
+        &self,
+        context: &RequestContext,
+        documents: &[Document],
+        on_conflict: ConflictPolicy,
+    ) -> Result<ImportSummary, Error>;
+    async fn get_document(
+        &self,
+        context: &RequestContext,
+        document: &GetDocumentRequest,
+    ) -> Result<Document, Error>;
+    /// Returns the number of documents of each genre. Genres without documents may be
+    /// omitted.
+    async fn count_documents(&self, context: &RequestContext) -> Result<Vec<(Genre, u64)>, Error>;
+    /// Checks, as cheaply as possible, that the storage can serve requests.
+    async fn health_check(&self, context: &RequestContext) -> Result<(), Error>;
 }
@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+
+use crate::model::api_key::{
+    generate_secret, hash_secret, ApiKey, CreateApiKeyRequest, CreatedApiKey, RevokeApiKeyRequest,
+};
+use crate::model::authorization::{Permission, Principal};
+use crate::model::context::RequestContext;
+use crate::model::error::Error;
+use crate::ports::primary::api_key::ApiKeyManagement;
+use crate::ports::secondary::api_key::ApiKeyStorage;
+use crate::ports::secondary::authorization::AuthorizationPolicy;
+use crate::ports::secondary::clock::{Clock, SystemClock};
+use crate::ports::secondary::id::{IdGenerator, RandomIdGenerator};
+
+// A wrapper around a secondary storage, which checks with the authorization policy
+// that the request's principal is allowed to make the request before forwarding it.
+pub struct Authorized<T> {
+    storage: T,
+    policy: Box<dyn AuthorizationPolicy + Send + Sync>,
+    clock: Box<dyn Clock + Send + Sync>,
+    ids: Box<dyn IdGenerator + Send + Sync>,
+}
+
+impl<T> Authorized<T> {
+    /// Creates a wrapper with the system clock and random ids.
+    pub fn new(storage: T, policy: Box<dyn AuthorizationPolicy + Send + Sync>) -> Self {
+        Authorized {
+            storage,
+            policy,
+            clock: Box::new(SystemClock),
+            ids: Box::new(RandomIdGenerator),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Box<dyn Clock + Send + Sync>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_id_generator(mut self, ids: Box<dyn IdGenerator + Send + Sync>) -> Self {
+        self.ids = ids;
+        self
+    }
+}
+
+#[async_trait]
+impl<T> ApiKeyManagement for Authorized<T>
+where
+    T: ApiKeyStorage + Send + Sync,
+{
+    async fn create_api_key(
+        &self,
+        context: &RequestContext,
+        request: &CreateApiKeyRequest,
+    ) -> Result<CreatedApiKey, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Admin)?;
+        let secret = generate_secret();
+        let key = ApiKey {
+            id: self.ids.generate(),
+            name: request.name.clone(),
+            scopes: request.scopes.clone(),
+            created_at: self.clock.now(),
+            expires_at: request.expires_at,
+            revoked_at: None,
+        };
+        let key = self
+            .storage
+            .add_api_key(context, &key, &hash_secret(&secret))
+            .await?;
+        Ok(CreatedApiKey { key, secret })
+    }
+
+    async fn revoke_api_key(
+        &self,
+        context: &RequestContext,
+        request: &RevokeApiKeyRequest,
+    ) -> Result<ApiKey, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Admin)?;
+        self.storage.revoke_api_key(context, &request.id).await
+    }
+
+    async fn list_api_keys(&self, context: &RequestContext) -> Result<Vec<ApiKey>, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Admin)?;
+        self.storage.list_api_keys(context).await
+    }
+
+    async fn authenticate_api_key(
+        &self,
+        context: &RequestContext,
+        secret: &str,
+    ) -> Result<Principal, Error> {
+        match self
+            .storage
+            .find_api_key(context, &hash_secret(secret))
+            .await?
+        {
+            Some(key) if key.is_active(self.clock.now()) => Ok(Principal {
+                subject: Some(format!("api-key:{}", key.id)),
+                roles: Vec::new(),
+                scopes: Some(key.scopes),
+            }),
+            _ => Err(Error::InvalidApiKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ports::secondary::api_key::MockApiKeyStorage;
+    use crate::ports::secondary::authorization::MockAuthorizationPolicy;
+    use crate::ports::secondary::clock::FixedClock;
+    use crate::ports::secondary::id::SequentialIdGenerator;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2022, 3, 1).and_hms(10, 0, 0)
+    }
+
+    fn key(now: DateTime<Utc>) -> ApiKey {
+        ApiKey {
+            id: Uuid::from_u128(1),
+            name: String::from("importer"),
+            scopes: vec![Permission::Write],
+            created_at: now,
+            expires_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn allow_all() -> Box<MockAuthorizationPolicy> {
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+        Box::new(policy)
+    }
+
+    #[tokio::test]
+    async fn should_create_a_key_storing_only_the_hash_of_the_secret() {
+        let stored = Arc::new(Mutex::new(String::new()));
+        let mut storage = MockApiKeyStorage::new();
+        {
+            let stored = stored.clone();
+            storage
+                .expect_add_api_key()
+                .times(1)
+                .returning(move |_, key, hash| {
+                    *stored.lock().unwrap() = hash.to_string();
+                    Ok(key.clone())
+                });
+        }
+        let api_keys = Authorized::new(storage, allow_all())
+            .with_clock(Box::new(FixedClock::new(now())))
+            .with_id_generator(Box::new(SequentialIdGenerator::starting_at(1)));
+        let request = CreateApiKeyRequest {
+            name: String::from("importer"),
+            scopes: vec![Permission::Write],
+            expires_at: None,
+        };
+        let created = api_keys
+            .create_api_key(&RequestContext::default(), &request)
+            .await
+            .unwrap();
+        assert_eq!(created.key.id, Uuid::from_u128(1));
+        assert_eq!(created.key.name, "importer");
+        assert_eq!(created.key.created_at, now());
+        assert_eq!(*stored.lock().unwrap(), hash_secret(&created.secret));
+        assert_ne!(*stored.lock().unwrap(), created.secret);
+    }
+
+    #[tokio::test]
+    async fn should_revoke_a_key() {
+        let now = now();
+        let mut storage = MockApiKeyStorage::new();
+        storage
+            .expect_revoke_api_key()
+            .withf(|_, id| *id == Uuid::from_u128(1))
+            .times(1)
+            .returning(move |_, _| {
+                Ok(ApiKey {
+                    revoked_at: Some(now),
+                    ..key(now)
+                })
+            });
+        let api_keys = Authorized::new(storage, allow_all());
+        let request = RevokeApiKeyRequest {
+            id: Uuid::from_u128(1),
+        };
+        let key = api_keys
+            .revoke_api_key(&RequestContext::default(), &request)
+            .await
+            .unwrap();
+        assert_eq!(key.revoked_at, Some(now));
+    }
+
+    #[tokio::test]
+    async fn should_only_authenticate_active_keys() {
+        let now = now();
+        let mut storage = MockApiKeyStorage::new();
+        storage
+            .expect_find_api_key()
+            .withf(|_, hash| hash == hash_secret("active"))
+            .returning(move |_, _| Ok(Some(key(now))));
+        storage
+            .expect_find_api_key()
+            .withf(|_, hash| hash == hash_secret("expired"))
+            .returning(move |_, _| {
+                Ok(Some(ApiKey {
+                    expires_at: Some(now - Duration::days(1)),
+                    ..key(now)
+                }))
+            });
+        storage
+            .expect_find_api_key()
+            .withf(|_, hash| hash == hash_secret("unknown"))
+            .returning(|_, _| Ok(None));
+        storage
+            .expect_find_api_key()
+            .withf(|_, hash| hash == hash_secret("unavailable"))
+            .returning(|_, _| {
+                Err(Error::Storage {
+                    source: "connection refused".into(),
+                })
+            });
+        let api_keys = Authorized::new(storage, Box::new(MockAuthorizationPolicy::new()))
+            .with_clock(Box::new(FixedClock::new(now)));
+        let context = RequestContext::default();
+
+        let principal = api_keys
+            .authenticate_api_key(&context, "active")
+            .await
+            .unwrap();
+        assert_eq!(
+            principal.subject,
+            Some(format!("api-key:{}", Uuid::from_u128(1)))
+        );
+        assert_eq!(principal.scopes, Some(vec![Permission::Write]));
+        for secret in ["expired", "unknown"] {
+            let result = api_keys.authenticate_api_key(&context, secret).await;
+            assert!(matches!(result, Err(Error::InvalidApiKey)), "{}", secret);
+        }
+        // Storage failures are not confused with invalid keys.
+        let result = api_keys.authenticate_api_key(&context, "unavailable").await;
+        assert!(matches!(result, Err(Error::Storage { .. })));
+    }
+}
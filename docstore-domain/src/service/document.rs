@@ -0,0 +1,428 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::model::authorization::Permission;
+use crate::model::context::RequestContext;
+use crate::model::document::{
+    AddDocumentRequest, BulkAddDocumentsRequest, BulkAddOutcome, Document, ExportDocumentsRequest,
+    GetDocumentRequest, ImportDocumentsRequest, ImportSummary, ListDocumentsByAuthorRequest,
+    ListDocumentsRequest,
+};
+use crate::model::error::Error;
+use crate::model::event::{DocumentEvent, DocumentEventKind};
+use crate::model::validation::{validate_add_document, FieldError, ValidationConfig};
+use crate::ports::primary;
+use crate::ports::secondary::authorization::AuthorizationPolicy;
+use crate::ports::secondary::clock::{Clock, SystemClock};
+use crate::ports::secondary::events::EventPublisher;
+use crate::ports::secondary::id::{IdGenerator, RandomIdGenerator};
+use crate::ports::secondary::storage::DocumentStorage;
+
+/// The business rules applied to documents, between the primary port and the storage:
+/// requests are authorized and validated before they are forwarded, and changes are
+/// published as events.
+pub struct DocumentService {
+    storage: Box<dyn DocumentStorage + Send + Sync>,
+    policy: Box<dyn AuthorizationPolicy + Send + Sync>,
+    validation: ValidationConfig,
+    clock: Box<dyn Clock + Send + Sync>,
+    ids: Box<dyn IdGenerator + Send + Sync>,
+    events: Option<Arc<dyn EventPublisher + Send + Sync>>,
+}
+
+impl DocumentService {
+    /// Creates a service with the default validation, the system clock, random ids,
+    /// and no event publisher.
+    pub fn new(
+        storage: Box<dyn DocumentStorage + Send + Sync>,
+        policy: Box<dyn AuthorizationPolicy + Send + Sync>,
+    ) -> Self {
+        DocumentService {
+            storage,
+            policy,
+            validation: ValidationConfig::default(),
+            clock: Box::new(SystemClock),
+            ids: Box::new(RandomIdGenerator),
+            events: None,
+        }
+    }
+
+    pub fn with_validation(mut self, validation: ValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Box<dyn Clock + Send + Sync>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_id_generator(mut self, ids: Box<dyn IdGenerator + Send + Sync>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    pub fn with_event_publisher(mut self, events: Arc<dyn EventPublisher + Send + Sync>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // Builds the document added by the request, attributed to the context's principal.
+    fn new_document(
+        &self,
+        context: &RequestContext,
+        request: AddDocumentRequest,
+        now: DateTime<Utc>,
+    ) -> Document {
+        Document {
+            id: request.id.unwrap_or_else(|| self.ids.generate()),
+            title: request.title,
+            outline: request.outline,
+            content: request.content,
+            html: request.html,
+            tags: request.tags,
+            genre: request.genre,
+            created_at: now,
+            updated_at: now,
+            created_by: context.principal.subject.clone(),
+            updated_by: context.principal.subject.clone(),
+        }
+    }
+
+    fn publish(&self, context: &RequestContext, kind: DocumentEventKind, document: &Document) {
+        if let Some(events) = &self.events {
+            events.publish(DocumentEvent {
+                id: self.ids.generate(),
+                kind,
+                document: document.clone(),
+                occurred_at: self.clock.now(),
+                actor: context.principal.subject.clone(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl primary::storage::DocumentStorage for DocumentService {
+    async fn list_documents(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsRequest,
+    ) -> Result<Vec<Document>, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Read)?;
+        self.storage.list_documents(context, request).await
+    }
+
+    async fn list_documents_by_author(
+        &self,
+        context: &RequestContext,
+        request: &ListDocumentsByAuthorRequest,
+    ) -> Result<Vec<Document>, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Read)?;
+        self.storage
+            .list_documents_by_author(context, request)
+            .await
+    }
+
+    async fn export_documents(
+        &self,
+        context: &RequestContext,
+        request: &ExportDocumentsRequest,
+    ) -> Result<Vec<Document>, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Read)?;
+        self.storage.export_documents(context, request).await
+    }
+
+    async fn add_document(
+        &self,
+        context: &RequestContext,
+        request: &AddDocumentRequest,
+    ) -> Result<Document, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Write)?;
+        let request = validate_add_document(request, &self.validation)
+            .map_err(|errors| Error::Validation { errors })?;
+        let document = self.new_document(context, request, self.clock.now());
+        let document = self.storage.add_document(context, &document).await?;
+        self.publish(context, DocumentEventKind::Created, &document);
+        Ok(document)
+    }
+
+    async fn bulk_add_documents(
+        &self,
+        context: &RequestContext,
+        request: &BulkAddDocumentsRequest,
+    ) -> Result<Vec<BulkAddOutcome>, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Write)?;
+        if request.documents.len() > self.validation.max_batch_size {
+            return Err(Error::Validation {
+                errors: vec![FieldError::new(
+                    "documents",
+                    format!(
+                        "must contain at most {} documents",
+                        self.validation.max_batch_size
+                    ),
+                )],
+            });
+        }
+        let content_size = request
+            .documents
+            .iter()
+            .map(|document| document.content.len() + document.html.len())
+            .sum::<usize>();
+        if content_size > self.validation.max_batch_content_size {
+            return Err(Error::Validation {
+                errors: vec![FieldError::new(
+                    "documents",
+                    format!(
+                        "must have contents and htmls of at most {} bytes in total",
+                        self.validation.max_batch_content_size
+                    ),
+                )],
+            });
+        }
+
+        // Outcomes are known upfront for invalid and duplicated documents, the others
+        // are decided by the storage.
+        let now = self.clock.now();
+        let mut outcomes = Vec::with_capacity(request.documents.len());
+        let mut documents = Vec::new();
+        let mut positions = Vec::new();
+        let mut ids = HashSet::new();
+        for (position, request) in request.documents.iter().enumerate() {
+            match validate_add_document(request, &self.validation) {
+                Err(errors) => outcomes.push(Some(BulkAddOutcome::Invalid { errors })),
+                Ok(request) => {
+                    let document = self.new_document(context, request, now);
+                    if !ids.insert(document.id) {
+                        outcomes.push(Some(BulkAddOutcome::Conflict { id: document.id }));
+                        continue;
+                    }
+                    outcomes.push(None);
+                    positions.push(position);
+                    documents.push(document);
+                }
+            }
+        }
+
+        let rejected = outcomes.iter().any(Option::is_some);
+        if !(request.atomic && rejected) && !documents.is_empty() {
+            let stored = self
+                .storage
+                .bulk_add_documents(context, &documents, request.atomic)
+                .await?;
+            let conflict = stored.iter().any(Option::is_none);
+            for ((position, document), stored) in positions.into_iter().zip(documents).zip(stored) {
+                outcomes[position] = match stored {
+                    None => Some(BulkAddOutcome::Conflict { id: document.id }),
+                    Some(_) if request.atomic && conflict => None,
+                    Some(document) => {
+                        self.publish(context, DocumentEventKind::Created, &document);
+                        Some(BulkAddOutcome::Created { document })
+                    }
+                };
+            }
+        }
+
+        Ok(outcomes
+            .into_iter()
+            .map(|outcome| outcome.unwrap_or(BulkAddOutcome::Aborted))
+            .collect())
+    }
+
+    // Imported documents are restored as they are, so they are not validated, and the
+    // import requires the admin permission. A document given twice would be written twice
+    // by the storage, so the request is rejected instead.
+    async fn import_documents(
+        &self,
+        context: &RequestContext,
+        request: &ImportDocumentsRequest,
+    ) -> Result<ImportSummary, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Admin)?;
+        let mut ids = HashSet::new();
+        let duplicates = request
+            .documents
+            .iter()
+            .filter(|document| !ids.insert(document.id))
+            .map(|document| document.id.to_string())
+            .collect::<Vec<_>>();
+        if !duplicates.is_empty() {
+            return Err(Error::Validation {
+                errors: vec![FieldError::new(
+                    "documents",
+                    format!("must have unique ids, found {}", duplicates.join(", ")),
+                )],
+            });
+        }
+        self.storage
+            .import_documents(context, &request.documents, request.on_conflict)
+            .await
+    }
+
+    async fn get_document(
+        &self,
+        context: &RequestContext,
+        request: &GetDocumentRequest,
+    ) -> Result<Document, Error> {
+        self.policy
+            .authorize(&context.principal, Permission::Read)?;
+        self.storage.get_document(context, request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::authorization::Principal;
+    use crate::model::document::{ConflictPolicy, Genre};
+    use crate::ports::primary::storage::DocumentStorage as _;
+    use crate::ports::secondary::authorization::MockAuthorizationPolicy;
+    use crate::ports::secondary::clock::FixedClock;
+    use crate::ports::secondary::events::MockEventPublisher;
+    use crate::ports::secondary::id::SequentialIdGenerator;
+    use crate::ports::secondary::storage::MockDocumentStorage;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn request() -> AddDocumentRequest {
+        AddDocumentRequest {
+            id: None,
+            title: String::from("Getting Started"),
+            outline: String::from("abstract"),
+            content: String::from("content"),
+            html: String::from("<p>content</p>"),
+            tags: vec![String::from("rust")],
+            genre: Genre::Tutorial,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_build_the_document_and_publish_an_event() {
+        let now = Utc.ymd(2022, 3, 22).and_hms(10, 0, 0);
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_add_document()
+            .times(1)
+            .returning(|_, document| Ok(document.clone()));
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+        let mut events = MockEventPublisher::new();
+        events
+            .expect_publish()
+            .withf(move |event| {
+                event.id == Uuid::from_u128(2)
+                    && event.kind == DocumentEventKind::Created
+                    && event.occurred_at == now
+                    && event.actor.as_deref() == Some("alice")
+            })
+            .times(1)
+            .return_const(());
+
+        let service = DocumentService::new(Box::new(storage), Box::new(policy))
+            .with_clock(Box::new(FixedClock::new(now)))
+            .with_id_generator(Box::new(SequentialIdGenerator::starting_at(1)))
+            .with_event_publisher(Arc::new(events));
+        let context = RequestContext::new(Principal {
+            subject: Some(String::from("alice")),
+            roles: vec![String::from("editor")],
+            scopes: None,
+        });
+        let document = service.add_document(&context, &request()).await.unwrap();
+        assert_eq!(document.id, Uuid::from_u128(1));
+        assert_eq!(document.created_at, now);
+        assert_eq!(document.updated_at, now);
+        assert_eq!(document.created_by.as_deref(), Some("alice"));
+    }
+
+    #[tokio::test]
+    async fn should_abort_an_atomic_bulk_request_with_an_invalid_document() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_bulk_add_documents().never();
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+
+        let service = DocumentService::new(Box::new(storage), Box::new(policy));
+        let mut invalid = request();
+        invalid.title = String::new();
+        let request = BulkAddDocumentsRequest {
+            documents: vec![request(), invalid],
+            atomic: true,
+        };
+        let outcomes = service
+            .bulk_add_documents(&RequestContext::default(), &request)
+            .await
+            .unwrap();
+        assert!(matches!(outcomes[0], BulkAddOutcome::Aborted));
+        assert!(matches!(outcomes[1], BulkAddOutcome::Invalid { .. }));
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_bulk_request_above_the_content_size() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_bulk_add_documents().never();
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+
+        let service = DocumentService::new(Box::new(storage), Box::new(policy)).with_validation(
+            ValidationConfig {
+                max_batch_content_size: 30,
+                ..ValidationConfig::default()
+            },
+        );
+        // Each request has 21 bytes of content and html.
+        let request = BulkAddDocumentsRequest {
+            documents: vec![request(), request()],
+            atomic: false,
+        };
+        let result = service
+            .bulk_add_documents(&RequestContext::default(), &request)
+            .await;
+        assert!(matches!(result, Err(Error::Validation { .. })));
+    }
+
+    #[tokio::test]
+    async fn should_reject_an_import_with_duplicated_ids() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_import_documents().never();
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+
+        let service = DocumentService::new(Box::new(storage), Box::new(policy));
+        let now = Utc.ymd(2022, 4, 5).and_hms(10, 0, 0);
+        let document = service.new_document(&RequestContext::default(), request(), now);
+        let request = ImportDocumentsRequest {
+            documents: vec![document.clone(), document],
+            on_conflict: ConflictPolicy::Overwrite,
+        };
+        let result = service
+            .import_documents(&RequestContext::default(), &request)
+            .await;
+        assert!(matches!(result, Err(Error::Validation { .. })));
+    }
+
+    #[tokio::test]
+    async fn should_not_reach_the_storage_when_permission_is_denied() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_add_document().never();
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, permission| {
+            Err(Error::PermissionDenied {
+                subject: String::from("anonymous"),
+                permission,
+            })
+        });
+
+        let service = DocumentService::new(Box::new(storage), Box::new(policy));
+        let result = service
+            .add_document(&RequestContext::default(), &request())
+            .await;
+        assert!(matches!(result, Err(Error::PermissionDenied { .. })));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod authorized;
+pub mod document;
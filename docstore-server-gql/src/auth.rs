@@ -0,0 +1,331 @@
+use docstore_adapter_1ry_gql::auth::Claims;
+use docstore_domain::model::authorization::Principal;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::error::Error as ModelError;
+use docstore_domain::ports::primary::api_key::ApiKeyManagement;
+use http::HeaderMap;
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use snafu::{ResultExt, Snafu};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::settings::{Auth, Authorization};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Cannot read key file {}: {}", path.display(), source))]
+    ReadKey {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Invalid key in {}: {}", path.display(), source))]
+    InvalidKey {
+        path: PathBuf,
+        source: jsonwebtoken::errors::Error,
+    },
+
+    #[snafu(display("Invalid JWKS in {}: {}", path.display(), source))]
+    InvalidJwks {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Invalid token: {}", source))]
+    InvalidToken { source: jsonwebtoken::errors::Error },
+
+    #[snafu(display("No key to validate token with kid {:?}", kid))]
+    UnknownKey { kid: Option<String> },
+
+    #[snafu(display("Algorithm {:?} cannot be used {}", algorithm, details))]
+    UnsupportedAlgorithm {
+        algorithm: Algorithm,
+        details: String,
+    },
+}
+
+/// A rejection for requests whose credentials are not accepted.
+#[derive(Debug)]
+pub enum AuthRejection {
+    /// The credentials are invalid.
+    Unauthorized { msg: String },
+    /// The credentials could not be checked, eg. because the API keys storage failed.
+    /// The details are only logged, since they may reveal the database.
+    Unavailable,
+}
+
+impl warp::reject::Reject for AuthRejection {}
+
+// The keys used to validate the token signature. With a JWKS, the key is selected
+// with the 'kid' found in the token's header.
+enum Keys {
+    None,
+    Single(DecodingKey),
+    Set(HashMap<String, DecodingKey>),
+}
+
+fn is_rsa(algorithm: Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512
+    )
+}
+
+// Secret files usually end with a newline, which is not part of the secret. Only that
+// line ending is removed: other whitespaces may be part of the secret.
+fn trim(secret: &[u8]) -> &[u8] {
+    let secret = secret.strip_suffix(b"\n").unwrap_or(secret);
+    secret.strip_suffix(b"\r").unwrap_or(secret)
+}
+
+pub struct Authenticator {
+    keys: Keys,
+    validation: Validation,
+}
+
+impl Authenticator {
+    pub fn new(config: &Auth) -> Result<Self, Error> {
+        let keys = match (&config.key_path, &config.jwks_path) {
+            (_, Some(path)) => {
+                if !is_rsa(config.algorithm) {
+                    return UnsupportedAlgorithm {
+                        algorithm: config.algorithm,
+                        details: "with a JWKS, which holds RSA keys",
+                    }
+                    .fail();
+                }
+                let jwks = std::fs::read(path).context(ReadKey { path })?;
+                let jwks: JwkSet = serde_json::from_slice(&jwks).context(InvalidJwks { path })?;
+                let keys = jwks
+                    .keys
+                    .iter()
+                    .filter_map(|jwk| match (&jwk.common.key_id, &jwk.algorithm) {
+                        (Some(kid), AlgorithmParameters::RSA(rsa)) => Some(
+                            DecodingKey::from_rsa_components(&rsa.n, &rsa.e)
+                                .map(|key| (kid.clone(), key)),
+                        ),
+                        _ => None,
+                    })
+                    .collect::<Result<HashMap<_, _>, _>>()
+                    .context(InvalidKey { path })?;
+                Keys::Set(keys)
+            }
+            (Some(path), None) => {
+                let key = std::fs::read(path).context(ReadKey { path })?;
+                let key = match config.algorithm {
+                    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                        DecodingKey::from_secret(trim(&key))
+                    }
+                    Algorithm::RS256
+                    | Algorithm::RS384
+                    | Algorithm::RS512
+                    | Algorithm::PS256
+                    | Algorithm::PS384
+                    | Algorithm::PS512 => {
+                        DecodingKey::from_rsa_pem(&key).context(InvalidKey { path })?
+                    }
+                    Algorithm::ES256 | Algorithm::ES384 => {
+                        DecodingKey::from_ec_pem(&key).context(InvalidKey { path })?
+                    }
+                    Algorithm::EdDSA => {
+                        DecodingKey::from_ed_pem(&key).context(InvalidKey { path })?
+                    }
+                };
+                Keys::Single(key)
+            }
+            (None, None) => Keys::None,
+        };
+
+        let mut validation = Validation::new(config.algorithm);
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        if let Some(audience) = &config.audience {
+            validation.set_audience(&[audience]);
+        }
+
+        Ok(Authenticator { keys, validation })
+    }
+
+    pub fn authenticate(&self, token: &str) -> Result<Claims, Error> {
+        let key = match &self.keys {
+            Keys::None => None,
+            Keys::Single(key) => Some(key),
+            Keys::Set(keys) => {
+                let header = decode_header(token).context(InvalidToken)?;
+                header.kid.as_ref().and_then(|kid| keys.get(kid))
+            }
+        };
+        let key = key.ok_or_else(|| Error::UnknownKey {
+            kid: decode_header(token).ok().and_then(|header| header.kid),
+        })?;
+        decode::<Claims>(token, key, &self.validation)
+            .map(|data| data.claims)
+            .context(InvalidToken)
+    }
+}
+
+/// Returns the principal making the request. In order, it is authenticated with:
+/// - the API key found in the 'X-Api-Key' header,
+/// - the bearer token found in the 'Authorization' header,
+/// - the request headers, if they are trusted.
+/// Requests without any of these are anonymous, and requests with invalid credentials
+/// are rejected.
+pub async fn authenticate(
+    context: &RequestContext,
+    headers: &HeaderMap,
+    authenticator: &Authenticator,
+    api_keys: &(dyn ApiKeyManagement + Send + Sync),
+    config: &Authorization,
+) -> Result<Principal, AuthRejection> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    if let Some(secret) = header("x-api-key") {
+        return api_keys
+            .authenticate_api_key(context, secret)
+            .await
+            .map_err(|err| match err {
+                ModelError::InvalidApiKey => AuthRejection::Unauthorized {
+                    msg: err.to_string(),
+                },
+                err => {
+                    tracing::error!("Could not authenticate the API key: {}", err);
+                    AuthRejection::Unavailable
+                }
+            });
+    }
+
+    if let Some(authorization) = header("authorization") {
+        let token =
+            authorization
+                .strip_prefix("Bearer ")
+                .ok_or_else(|| AuthRejection::Unauthorized {
+                    msg: String::from("expected a bearer token"),
+                })?;
+        let claims =
+            authenticator
+                .authenticate(token)
+                .map_err(|err| AuthRejection::Unauthorized {
+                    msg: err.to_string(),
+                })?;
+        return Ok(Principal {
+            subject: Some(claims.sub),
+            roles: claims.roles,
+            scopes: None,
+        });
+    }
+
+    if !config.trust_headers {
+        return Ok(Principal::anonymous());
+    }
+    match header(&config.subject_header) {
+        Some(subject) => Ok(Principal {
+            subject: Some(subject.to_string()),
+            roles: header(&config.roles_header)
+                .map(|roles| {
+                    roles
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|role| !role.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            scopes: None,
+        }),
+        None => Ok(Principal::anonymous()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use uuid::Uuid;
+
+    fn authenticator(algorithm: Algorithm, secret: &str) -> Result<Authenticator, Error> {
+        // A file for each call, since tests run concurrently.
+        let path = std::env::temp_dir().join(format!("docstore-auth-{}.key", Uuid::new_v4()));
+        std::fs::write(&path, secret).unwrap();
+        let authenticator = Authenticator::new(&Auth {
+            algorithm,
+            key_path: Some(path.clone()),
+            jwks_path: None,
+            issuer: None,
+            audience: Some(String::from("docstore")),
+        });
+        std::fs::remove_file(&path).unwrap();
+        authenticator
+    }
+
+    fn token(algorithm: Algorithm, exp: i64, aud: &str) -> String {
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "exp": exp,
+            "aud": aud,
+            "roles": ["writer"],
+        });
+        encode(
+            &Header::new(algorithm),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_validate_tokens() {
+        // The secret file ends with a newline, which is not part of the secret.
+        let authenticator = authenticator(Algorithm::HS256, "secret\n").unwrap();
+        let exp = chrono::Utc::now().timestamp() + 3600;
+
+        let claims = authenticator
+            .authenticate(&token(Algorithm::HS256, exp, "docstore"))
+            .unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.roles, vec![String::from("writer")]);
+
+        let expired = chrono::Utc::now().timestamp() - 3600;
+        for token in [
+            token(Algorithm::HS256, expired, "docstore"),
+            token(Algorithm::HS256, exp, "other"),
+            token(Algorithm::HS384, exp, "docstore"),
+        ] {
+            let err = authenticator.authenticate(&token).unwrap_err();
+            assert!(matches!(err, Error::InvalidToken { .. }), "{}", err);
+        }
+
+        // Other whitespaces are part of the secret.
+        let spaced = authenticator(Algorithm::HS256, " secret\n").unwrap();
+        assert!(spaced
+            .authenticate(&token(Algorithm::HS256, exp, "docstore"))
+            .is_err());
+    }
+
+    #[test]
+    fn should_reject_a_jwks_with_hmac() {
+        let err = Authenticator::new(&Auth {
+            algorithm: Algorithm::HS256,
+            key_path: None,
+            // Rejected before the file is read.
+            jwks_path: Some(std::env::temp_dir().join(format!("docstore-{}.json", Uuid::new_v4()))),
+            issuer: None,
+            audience: None,
+        })
+        .err()
+        .unwrap();
+        assert!(matches!(err, Error::UnsupportedAlgorithm { .. }), "{}", err);
+    }
+}
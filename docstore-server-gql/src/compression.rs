@@ -0,0 +1,167 @@
+use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder};
+use http::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, ETAG, VARY};
+use http::{HeaderMap, StatusCode};
+use tokio_stream::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+use warp::hyper::body::{Body, HttpBody};
+use warp::reply::Response;
+use warp::Reply;
+
+// Smaller bodies are sent as they are: compression would barely reduce them.
+const MIN_SIZE: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// The encoding preferred by the client among the supported ones, given the value of
+/// its 'Accept-Encoding' header, or None when the body must be sent as it is.
+/// On equal quality values, brotli is preferred to gzip, and gzip to deflate.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let entries = accept_encoding
+        .split(',')
+        .filter_map(|entry| {
+            let mut params = entry.split(';');
+            let coding = params.next()?.trim().to_ascii_lowercase();
+            let quality = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.trim().parse::<f32>().ok())?;
+            Some((coding, quality))
+        })
+        .collect::<Vec<_>>();
+    let quality = |coding: &str| {
+        entries
+            .iter()
+            .find(|(name, _)| name == coding)
+            .or_else(|| entries.iter().find(|(name, _)| name == "*"))
+            .map_or(0.0, |(_, quality)| *quality)
+    };
+    [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate]
+        .iter()
+        .map(|encoding| (*encoding, quality(encoding.as_str())))
+        .filter(|(_, quality)| *quality > 0.0)
+        .fold(
+            None,
+            |best: Option<(Encoding, f32)>, (encoding, quality)| match best {
+                Some((_, best_quality)) if best_quality >= quality => best,
+                _ => Some((encoding, quality)),
+            },
+        )
+        .map(|(encoding, _)| encoding)
+}
+
+fn compressible(response: &Response) -> bool {
+    !matches!(
+        response.status(),
+        StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED
+    ) && !response.headers().contains_key(CONTENT_ENCODING)
+        && HttpBody::size_hint(response.body())
+            .exact()
+            .map_or(true, |size| size >= MIN_SIZE)
+}
+
+// The compressed body is not the one whose bytes the ETag identifies, so a strong
+// ETag is made weak: it still identifies the content, for 'If-None-Match'.
+fn weaken_etag(headers: &mut HeaderMap) {
+    let weak = match headers.get(ETAG) {
+        Some(etag) if !etag.as_bytes().starts_with(b"W/") => {
+            HeaderValue::from_bytes(&[&b"W/"[..], etag.as_bytes()].concat())
+        }
+        _ => return,
+    };
+    if let Ok(weak) = weak {
+        headers.insert(ETAG, weak);
+    }
+}
+
+/// Compresses the body of the reply with the encoding negotiated from the request's
+/// 'Accept-Encoding' header. The body is compressed as it is streamed.
+pub fn compress(headers: &HeaderMap, reply: impl Reply) -> Response {
+    let mut response = reply.into_response();
+    let encoding = match headers
+        .get(ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .and_then(negotiate)
+    {
+        Some(encoding) if compressible(&response) => encoding,
+        Some(_) => {
+            // A 304 carries the ETag of the body the client would have received.
+            if response.status() == StatusCode::NOT_MODIFIED {
+                weaken_etag(response.headers_mut());
+            }
+            return response;
+        }
+        None => return response,
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let body = StreamReader::new(
+        body.map(|chunk| chunk.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))),
+    );
+    let body = match encoding {
+        Encoding::Brotli => Body::wrap_stream(ReaderStream::new(BrotliEncoder::new(body))),
+        Encoding::Gzip => Body::wrap_stream(ReaderStream::new(GzipEncoder::new(body))),
+        Encoding::Deflate => Body::wrap_stream(ReaderStream::new(DeflateEncoder::new(body))),
+    };
+    parts.headers.remove(CONTENT_LENGTH);
+    weaken_etag(&mut parts.headers);
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_negotiate_the_preferred_encoding() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(negotiate("gzip;q=1.0, br;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("deflate, gzip;q=0"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("*;q=0.1, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[tokio::test]
+    async fn should_compress_the_body() {
+        let body = "<entry>docstore</entry>".repeat(100);
+        let reply = warp::reply::with_header(body.clone(), "etag", "\"c8bc2586\"");
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+
+        let response = compress(&headers, reply);
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[ETAG], "W/\"c8bc2586\"");
+        let compressed = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(compressed.len() < body.len());
+        let mut decompressed = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(compressed.as_ref()),
+            &mut decompressed,
+        )
+        .unwrap();
+        assert_eq!(decompressed, body);
+    }
+}
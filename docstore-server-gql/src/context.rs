@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use docstore_adapter_1ry_grpc::service::{Access, ContextProvider};
+use docstore_domain::model::authorization::Principal;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::ports::primary::api_key::ApiKeyManagement;
+use http::HeaderMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+use uuid::Uuid;
+
+use super::auth::{self, AuthRejection, Authenticator};
+use super::rate_limit::{Operation, RateLimiter};
+use super::settings::Authorization;
+
+/// What is needed to build the context of a request.
+#[derive(Clone)]
+pub struct ContextBuilder {
+    pub authenticator: Arc<Authenticator>,
+    pub api_keys: Arc<dyn ApiKeyManagement + Send + Sync>,
+    pub authorization: Arc<Authorization>,
+    /// Maximum time allowed to serve a request, in milliseconds.
+    pub request_timeout: u64,
+}
+
+impl ContextBuilder {
+    /// Builds the context of the request from its headers:
+    /// - the request id is taken from 'X-Request-Id', or generated,
+    /// - the deadline is computed from 'X-Request-Timeout' (in milliseconds), or from
+    ///   'grpc-timeout', capped by the configured timeout,
+    /// - the locale is the first language found in 'Accept-Language',
+    /// - the principal is authenticated (see `auth::authenticate`).
+    pub async fn build(&self, headers: &HeaderMap) -> Result<RequestContext, AuthRejection> {
+        let mut context = RequestContext {
+            request_id: request_id(headers),
+            principal: Principal::anonymous(),
+            deadline: Some(Utc::now() + timeout(headers, self.request_timeout)),
+            locale: locale(headers),
+        };
+        context.principal = auth::authenticate(
+            &context,
+            headers,
+            &self.authenticator,
+            self.api_keys.as_ref(),
+            &self.authorization,
+        )
+        .await?;
+        // The request id is only known now, the root span of the request was created
+        // with an empty field for it (see `telemetry::request_span`).
+        tracing::Span::current()
+            .record("request_id", &tracing::field::display(&context.request_id));
+        Ok(context)
+    }
+}
+
+/// Builds the contexts of the gRPC requests, within the same budgets as the HTTP
+/// requests.
+pub struct GrpcContextProvider {
+    pub builder: ContextBuilder,
+    pub rate_limiter: Arc<RateLimiter>,
+}
+
+// gRPC metadata are HTTP/2 headers, so gRPC requests accept the same headers as the
+// HTTP routes.
+#[async_trait]
+impl ContextProvider for GrpcContextProvider {
+    async fn context(
+        &self,
+        metadata: &MetadataMap,
+        remote: Option<SocketAddr>,
+        access: Access,
+    ) -> Result<RequestContext, Status> {
+        let headers = metadata.clone().into_headers();
+        let operation = match access {
+            Access::Read => Operation::Query,
+            Access::Write => Operation::Mutation,
+        };
+        self.rate_limiter
+            .check_before_authentication(&headers, remote, operation)
+            .map_err(|limited| Status::resource_exhausted(limited.to_string()))?;
+        let context = self
+            .builder
+            .build(&headers)
+            .await
+            .map_err(|rejection| match rejection {
+                AuthRejection::Unauthorized { msg } => Status::unauthenticated(msg),
+                AuthRejection::Unavailable => Status::unavailable("Authentication is unavailable"),
+            })?;
+        self.rate_limiter
+            .check_after_authentication(&headers, &context, remote, operation)
+            .map_err(|limited| Status::resource_exhausted(limited.to_string()))?;
+        Ok(context)
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn request_id(headers: &HeaderMap) -> Uuid {
+    header(headers, "x-request-id")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(Uuid::new_v4)
+}
+
+// Clients can ask for a shorter timeout than the configured one, but not a longer one.
+// gRPC clients give theirs in 'grpc-timeout' instead of 'X-Request-Timeout'.
+fn timeout(headers: &HeaderMap, request_timeout: u64) -> Duration {
+    let millis = header(headers, "x-request-timeout")
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .into_iter()
+        .chain(header(headers, "grpc-timeout").and_then(grpc_timeout))
+        .fold(request_timeout, u64::min);
+    Duration::milliseconds(millis as i64)
+}
+
+// At most 8 digits followed by a unit, from hours ('H') to nanoseconds ('n'), as
+// defined by the gRPC over HTTP/2 protocol. Sub-millisecond timeouts are rounded up.
+fn grpc_timeout(value: &str) -> Option<u64> {
+    let unit = value.chars().last()?;
+    let digits = &value[..value.len() - unit.len_utf8()];
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = digits.parse::<u64>().ok()?;
+    match unit {
+        'H' => Some(value * 3_600_000),
+        'M' => Some(value * 60_000),
+        'S' => Some(value * 1000),
+        'm' => Some(value),
+        'u' => Some((value + 999) / 1000),
+        'n' => Some((value + 999_999) / 1_000_000),
+        _ => None,
+    }
+}
+
+fn locale(headers: &HeaderMap) -> Option<String> {
+    header(headers, "accept-language")
+        .and_then(|languages| languages.split(',').next())
+        .and_then(|language| language.split(';').next())
+        .map(str::trim)
+        .filter(|language| !language.is_empty() && *language != "*")
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn headers(values: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn should_read_the_request_id() {
+        let id = "0f8fad5b-d9cb-469f-a165-70867728950e";
+        assert_eq!(
+            request_id(&headers(&[("x-request-id", id)])),
+            Uuid::parse_str(id).unwrap()
+        );
+        // Missing or malformed ids are replaced with new ones.
+        assert_ne!(request_id(&headers(&[])), Uuid::nil());
+        let malformed = request_id(&headers(&[("x-request-id", "not-a-uuid")]));
+        assert_ne!(
+            malformed,
+            request_id(&headers(&[("x-request-id", "not-a-uuid")]))
+        );
+    }
+
+    #[test]
+    fn should_cap_the_timeout() {
+        let timeout = |value| super::timeout(&headers(&[("x-request-timeout", value)]), 5000);
+        assert_eq!(timeout("1000"), Duration::milliseconds(1000));
+        assert_eq!(timeout("60000"), Duration::milliseconds(5000));
+        assert_eq!(timeout("-1"), Duration::milliseconds(5000));
+        assert_eq!(timeout("soon"), Duration::milliseconds(5000));
+        assert_eq!(
+            super::timeout(&headers(&[]), 5000),
+            Duration::milliseconds(5000)
+        );
+    }
+
+    #[test]
+    fn should_read_the_grpc_timeout() {
+        let timeout = |value| super::timeout(&headers(&[("grpc-timeout", value)]), 5000);
+        assert_eq!(timeout("1S"), Duration::milliseconds(1000));
+        assert_eq!(timeout("250m"), Duration::milliseconds(250));
+        assert_eq!(timeout("1500u"), Duration::milliseconds(2));
+        assert_eq!(timeout("1H"), Duration::milliseconds(5000));
+        assert_eq!(timeout("123456789m"), Duration::milliseconds(5000));
+        assert_eq!(timeout("10s"), Duration::milliseconds(5000));
+        assert_eq!(timeout("m"), Duration::milliseconds(5000));
+        // The shortest of the timeouts is kept.
+        let both = headers(&[("grpc-timeout", "2S"), ("x-request-timeout", "3000")]);
+        assert_eq!(super::timeout(&both, 5000), Duration::milliseconds(2000));
+    }
+
+    #[test]
+    fn should_read_the_preferred_locale() {
+        let locale = |value| super::locale(&headers(&[("accept-language", value)]));
+        assert_eq!(
+            locale("fr-CH, fr;q=0.9, en;q=0.8"),
+            Some(String::from("fr-CH"))
+        );
+        assert_eq!(locale("de;q=0.7"), Some(String::from("de")));
+        assert_eq!(locale("*"), None);
+        assert_eq!(locale(" , en"), None);
+        assert_eq!(super::locale(&headers(&[])), None);
+    }
+}
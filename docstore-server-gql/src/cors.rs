@@ -0,0 +1,236 @@
+use http::header::{
+    HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use http::{HeaderMap, Method, StatusCode};
+use snafu::{ResultExt, Snafu};
+use std::str::FromStr;
+use std::sync::Arc;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use super::settings::Cors;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Invalid CORS method '{}': {}", method, source))]
+    InvalidMethod {
+        method: String,
+        source: http::method::InvalidMethod,
+    },
+
+    #[snafu(display("Invalid CORS header '{}': {}", header, source))]
+    InvalidHeader {
+        header: String,
+        source: http::header::InvalidHeaderName,
+    },
+}
+
+/// The request comes from an origin which is not allowed.
+#[derive(Debug)]
+pub struct Forbidden;
+
+impl warp::reject::Reject for Forbidden {}
+
+// Matches a value against a pattern in which '*' stands for any sequence of characters.
+fn matches(pattern: &str, value: &str) -> bool {
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    let (first, last) = match parts.as_slice() {
+        [exact] => return *exact == value,
+        [first, .., last] => (*first, *last),
+        [] => return false,
+    };
+    if value.len() < first.len() + last.len() || !value.starts_with(first) || !value.ends_with(last)
+    {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// The CORS policy of the HTTP routes, built from the settings.
+pub struct CorsPolicy {
+    origins: Vec<String>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    allow_credentials: bool,
+    max_age: u64,
+}
+
+impl CorsPolicy {
+    pub fn new(config: &Cors) -> Result<Self, Error> {
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_str(&method.to_ascii_uppercase()).context(InvalidMethod { method })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| HeaderName::from_str(header).context(InvalidHeader { header }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CorsPolicy {
+            origins: config
+                .allowed_origins
+                .iter()
+                .map(|origin| origin.to_ascii_lowercase())
+                .collect(),
+            methods,
+            headers,
+            allow_credentials: config.allow_credentials,
+            max_age: config.max_age,
+        })
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.origins.iter().any(|pattern| matches(pattern, &origin))
+    }
+
+    // The preflight request asks for a method, and for headers, which are all allowed.
+    fn allows_preflight(&self, headers: &HeaderMap) -> bool {
+        let method = headers
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok());
+        let method_allowed = method.map_or(false, |method| self.methods.contains(&method));
+        let headers_allowed = headers
+            .get_all(ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .all(|header| {
+                HeaderName::from_str(header).map_or(false, |header| self.headers.contains(&header))
+            });
+        method_allowed && headers_allowed
+    }
+
+    fn preflight(&self, headers: &HeaderMap) -> Response {
+        let origin = match headers.get(ORIGIN) {
+            Some(origin) => origin,
+            None => return StatusCode::NO_CONTENT.into_response(),
+        };
+        if !origin
+            .to_str()
+            .map_or(false, |origin| self.allows_origin(origin))
+            || !self.allows_preflight(headers)
+        {
+            return warp::reply::with_status("CORS request forbidden", StatusCode::FORBIDDEN)
+                .into_response();
+        }
+
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let response_headers = response.headers_mut();
+        self.allow_origin(response_headers, origin.clone());
+        response_headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            join(self.methods.iter().map(Method::as_str)),
+        );
+        if !self.headers.is_empty() {
+            response_headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                join(self.headers.iter().map(HeaderName::as_str)),
+            );
+        }
+        response_headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(self.max_age));
+        response
+    }
+
+    fn allow_origin(&self, headers: &mut HeaderMap, origin: HeaderValue) {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if self.allow_credentials {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        // The answer depends on the origin, caches must not share it between origins.
+        headers.append(VARY, HeaderValue::from_static("origin"));
+    }
+
+    /// Adds the CORS headers to the response of a request from an allowed origin.
+    pub fn apply(&self, headers: &HeaderMap, reply: impl Reply) -> Response {
+        let mut response = reply.into_response();
+        if let Some(origin) = headers.get(ORIGIN) {
+            if origin
+                .to_str()
+                .map_or(false, |origin| self.allows_origin(origin))
+            {
+                self.allow_origin(response.headers_mut(), origin.clone());
+            }
+        }
+        response
+    }
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a str>) -> HeaderValue {
+    HeaderValue::from_str(&values.collect::<Vec<_>>().join(", "))
+        .expect("methods and header names are valid header values")
+}
+
+/// Answers the preflight requests ('OPTIONS').
+pub fn preflight(
+    policy: Arc<CorsPolicy>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::options()
+        .and(warp::header::headers_cloned())
+        .map(move |headers: HeaderMap| policy.preflight(&headers))
+}
+
+// Browsers also send 'Origin' with the POST requests to their own origin, eg. from the
+// GraphQL playground.
+fn is_same_origin(origin: &str, host: Option<&str>) -> bool {
+    match (origin.split_once("://"), host) {
+        (Some((_, authority)), Some(host)) => authority.eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+/// Rejects the cross-origin requests from the origins which are not allowed. Requests
+/// without 'Origin' do not come from browsers, they are not concerned.
+pub fn guard(policy: Arc<CorsPolicy>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::header::optional::<String>("host"))
+        .and_then(move |origin: Option<String>, host: Option<String>| {
+            let allowed = origin.map_or(true, |origin| {
+                is_same_origin(&origin, host.as_deref()) || policy.allows_origin(&origin)
+            });
+            async move {
+                if allowed {
+                    Ok(())
+                } else {
+                    Err(warp::reject::custom(Forbidden))
+                }
+            }
+        })
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_origin_patterns() {
+        assert!(matches("*", "https://docs.example.com"));
+        assert!(matches("https://*.example.com", "https://docs.example.com"));
+        assert!(!matches("https://*.example.com", "https://example.com"));
+        assert!(!matches(
+            "https://*.example.com",
+            "https://docs.example.com.evil.org"
+        ));
+        assert!(matches("http://localhost:*", "http://localhost:3000"));
+        assert!(matches("https://example.com", "https://example.com"));
+        assert!(!matches("https://example.com", "https://example.com:8443"));
+    }
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::document::{Document, Genre, ListDocumentsRequest};
+use docstore_domain::model::error::{Error as ModelError, ErrorKind};
+use docstore_domain::model::validation::normalize_tag;
+use docstore_domain::ports::primary::storage::DocumentStorage;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use warp::http::Response as HttpResponse;
+use warp::reply::Response;
+use warp::Reply;
+
+use super::settings::Feed as FeedConfig;
+use super::utils::xml::escape;
+
+// Format of the dates in the 'Last-Modified' and 'If-Modified-Since' headers.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+/// The filters found in the query string of a feed, eg '/feed.atom?genre=tutorial'.
+#[derive(Debug, Default, Deserialize)]
+pub struct FeedQuery {
+    pub genre: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// Serves the most recently created documents as a feed. The response carries an
+/// 'ETag' and a 'Last-Modified' header, and has no body (304) when the client's copy,
+/// identified by 'If-None-Match' or 'If-Modified-Since', is still current.
+pub async fn feed(
+    service: &(dyn DocumentStorage + Send + Sync),
+    config: &FeedConfig,
+    format: FeedFormat,
+    query: &FeedQuery,
+    headers: &HeaderMap,
+    context: &RequestContext,
+) -> Response {
+    let genre = match query.genre.as_deref() {
+        None => None,
+        Some(name) => match parse_genre(name) {
+            Some(genre) => Some(genre),
+            None => {
+                return status(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Unknown genre '{}'", name),
+                )
+            }
+        },
+    };
+    let tag = query
+        .tag
+        .as_deref()
+        .map(normalize_tag)
+        .filter(|tag| !tag.is_empty());
+
+    let mut filters = Vec::new();
+    if let Some(genre) = &genre {
+        filters.push(genre.as_str().to_string());
+    }
+    if let Some(tag) = &tag {
+        filters.push(format!("#{}", tag));
+    }
+    let title = if filters.is_empty() {
+        config.title.clone()
+    } else {
+        format!("{}: {}", config.title, filters.join(", "))
+    };
+
+    let request = ListDocumentsRequest {
+        offset: 0,
+        limit: config.size,
+        genre,
+        tag,
+    };
+    let documents = match service.list_documents(context, &request).await {
+        Ok(documents) => documents,
+        Err(err) => return error(&err),
+    };
+
+    let last_modified = documents.iter().map(|document| document.updated_at).max();
+    let body = match format {
+        FeedFormat::Atom => atom(config, &title, &documents, last_modified),
+        FeedFormat::Rss => rss(config, &title, &documents, last_modified),
+    };
+    let etag = etag(&body);
+
+    let mut response = HttpResponse::builder().header("etag", &etag);
+    if let Some(last_modified) = last_modified {
+        response = response.header("last-modified", last_modified.format(HTTP_DATE).to_string());
+    }
+    let response = if not_modified(headers, &etag, last_modified) {
+        response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(String::new())
+    } else {
+        response
+            .header("content-type", format.content_type())
+            .body(body)
+    };
+    response
+        .map(Reply::into_response)
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+// Genres are given by name, 'to be decided' can also be given as 'tbd'.
+fn parse_genre(name: &str) -> Option<Genre> {
+    match name {
+        "tbd" | "to be decided" => Some(Genre::Tbd),
+        name => name
+            .parse()
+            .ok()
+            .filter(|genre| !matches!(genre, Genre::Tbd)),
+    }
+}
+
+fn status(code: StatusCode, msg: &str) -> Response {
+    warp::reply::with_status(msg.to_string(), code).into_response()
+}
+
+fn error(err: &ModelError) -> Response {
+    let code = match err.kind() {
+        ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        ErrorKind::Unauthenticated => StatusCode::UNAUTHORIZED,
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::Conflict => StatusCode::CONFLICT,
+        ErrorKind::InvalidArgument => StatusCode::BAD_REQUEST,
+        ErrorKind::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        ErrorKind::Internal => {
+            tracing::error!("Could not list the documents of the feed: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    status(code, &err.public_message())
+}
+
+// The ETag is a digest of the feed, so it changes whenever one of its entries does,
+// and only then: unlike the std hashers, sha256 does not change between releases.
+fn etag(body: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(body.as_bytes()));
+    format!("\"{}\"", &digest[..32])
+}
+
+// 'If-None-Match' takes precedence over 'If-Modified-Since' (RFC 7232, section 6).
+fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<DateTime<Utc>>) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(tags) = header("if-none-match") {
+        return tags
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+    let since =
+        header("if-modified-since").and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+    match (since, last_modified) {
+        // HTTP dates have a one second precision.
+        (Some(since), Some(last_modified)) => last_modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+fn document_url(config: &FeedConfig, document: &Document) -> String {
+    format!(
+        "{}/documents/{}.html",
+        config.base_url.trim_end_matches('/'),
+        document.id
+    )
+}
+
+fn atom(
+    config: &FeedConfig,
+    title: &str,
+    documents: &[Document],
+    updated: Option<DateTime<Utc>>,
+) -> String {
+    let rfc3339 = |date: &DateTime<Utc>| date.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let base_url = escape(config.base_url.trim_end_matches('/'));
+    let updated = updated.unwrap_or_else(|| Utc.timestamp(0, 0));
+
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+    feed.push_str(&format!("  <title>{}</title>\n", escape(title)));
+    feed.push_str(&format!("  <link href=\"{}/\"/>\n", base_url));
+    feed.push_str(&format!("  <id>{}/</id>\n", base_url));
+    feed.push_str(&format!("  <updated>{}</updated>\n", rfc3339(&updated)));
+    feed.push_str(&format!(
+        "  <author><name>{}</name></author>\n",
+        escape(&config.title)
+    ));
+    for document in documents {
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!("    <title>{}</title>\n", escape(&document.title)));
+        feed.push_str(&format!(
+            "    <link href=\"{}\"/>\n",
+            escape(&document_url(config, document))
+        ));
+        feed.push_str(&format!("    <id>urn:uuid:{}</id>\n", document.id));
+        feed.push_str(&format!(
+            "    <published>{}</published>\n",
+            rfc3339(&document.created_at)
+        ));
+        feed.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            rfc3339(&document.updated_at)
+        ));
+        if let Some(author) = &document.created_by {
+            feed.push_str(&format!(
+                "    <author><name>{}</name></author>\n",
+                escape(author)
+            ));
+        }
+        feed.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape(&document.outline)
+        ));
+        for tag in &document.tags {
+            feed.push_str(&format!("    <category term=\"{}\"/>\n", escape(tag)));
+        }
+        feed.push_str("  </entry>\n");
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+fn rss(
+    config: &FeedConfig,
+    title: &str,
+    documents: &[Document],
+    updated: Option<DateTime<Utc>>,
+) -> String {
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\">\n\
+         <channel>\n",
+    );
+    feed.push_str(&format!("  <title>{}</title>\n", escape(title)));
+    feed.push_str(&format!(
+        "  <link>{}/</link>\n",
+        escape(config.base_url.trim_end_matches('/'))
+    ));
+    feed.push_str(&format!("  <description>{}</description>\n", escape(title)));
+    if let Some(updated) = updated {
+        feed.push_str(&format!(
+            "  <lastBuildDate>{}</lastBuildDate>\n",
+            updated.to_rfc2822()
+        ));
+    }
+    for document in documents {
+        feed.push_str("  <item>\n");
+        feed.push_str(&format!("    <title>{}</title>\n", escape(&document.title)));
+        feed.push_str(&format!(
+            "    <link>{}</link>\n",
+            escape(&document_url(config, document))
+        ));
+        feed.push_str(&format!(
+            "    <guid isPermaLink=\"false\">urn:uuid:{}</guid>\n",
+            document.id
+        ));
+        feed.push_str(&format!(
+            "    <pubDate>{}</pubDate>\n",
+            document.created_at.to_rfc2822()
+        ));
+        feed.push_str(&format!(
+            "    <description>{}</description>\n",
+            escape(&document.outline)
+        ));
+        for tag in &document.tags {
+            feed.push_str(&format!("    <category>{}</category>\n", escape(tag)));
+        }
+        feed.push_str("  </item>\n");
+    }
+    feed.push_str("</channel>\n</rss>\n");
+    feed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn should_match_etag_before_modification_date() {
+        let last_modified = Some(Utc.ymd(2022, 4, 12).and_hms(10, 0, 0));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "if-modified-since",
+            HeaderValue::from_static("Tue, 12 Apr 2022 10:00:00 GMT"),
+        );
+        assert!(not_modified(&headers, "\"abc\"", last_modified));
+
+        headers.insert(
+            "if-none-match",
+            HeaderValue::from_static("W/\"abc\", \"def\""),
+        );
+        assert!(not_modified(&headers, "\"abc\"", last_modified));
+        assert!(!not_modified(&headers, "\"xyz\"", last_modified));
+
+        headers.remove("if-none-match");
+        let later = Some(Utc.ymd(2022, 4, 12).and_hms(10, 0, 1));
+        assert!(!not_modified(&headers, "\"abc\"", later));
+    }
+
+    #[test]
+    fn should_compute_a_stable_etag() {
+        assert_eq!(etag("feed"), "\"c8bc2586cdd87cd6f970fc4262c4bbc4\"");
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::Utc;
+use docstore_domain::model::authorization::Principal;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::ports::secondary::storage::DocumentStorage;
+use http::StatusCode;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use warp::reply::Response;
+use warp::Reply;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+/// The result of the check of a dependency.
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub status: Status,
+    /// Time taken by the check, in milliseconds.
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Up when every dependency is up.
+    pub status: Status,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+impl Reply for Report {
+    fn into_response(self) -> Response {
+        let status = match self.status {
+            Status::Up => StatusCode::OK,
+            Status::Down => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        warp::reply::with_status(warp::reply::json(&self), status).into_response()
+    }
+}
+
+/// The process is running: it does not check any dependency, so that the orchestrator
+/// does not restart the server when the database is down.
+pub fn live() -> Report {
+    Report {
+        status: Status::Up,
+        checks: BTreeMap::new(),
+    }
+}
+
+/// The server can serve requests: every dependency answered within `timeout`.
+pub async fn ready(storage: &(dyn DocumentStorage + Send + Sync), timeout: Duration) -> Report {
+    let mut checks = BTreeMap::new();
+    checks.insert("postgresql", check_storage(storage, timeout).await);
+    let status = if checks.values().all(|check| check.status == Status::Up) {
+        Status::Up
+    } else {
+        Status::Down
+    };
+    Report { status, checks }
+}
+
+async fn check_storage(storage: &(dyn DocumentStorage + Send + Sync), timeout: Duration) -> Check {
+    let context = RequestContext {
+        request_id: Uuid::new_v4(),
+        principal: Principal::anonymous(),
+        deadline: chrono::Duration::from_std(timeout)
+            .ok()
+            .map(|timeout| Utc::now() + timeout),
+        locale: None,
+    };
+    let start = Instant::now();
+    // The deadline bounds the query, the timeout also bounds the wait for a connection.
+    let result = tokio::time::timeout(timeout, storage.health_check(&context)).await;
+    let latency_ms = start.elapsed().as_millis();
+    match result {
+        Ok(Ok(())) => Check {
+            status: Status::Up,
+            latency_ms,
+            error: None,
+        },
+        Ok(Err(err)) => {
+            // The report is public, the details of the storage errors are only logged.
+            tracing::error!("Storage health check failed: {}", err);
+            Check {
+                status: Status::Down,
+                latency_ms,
+                error: Some(err.public_message()),
+            }
+        }
+        Err(_) => Check {
+            status: Status::Down,
+            latency_ms,
+            error: Some(format!("No answer within {}ms", timeout.as_millis())),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use docstore_domain::model::error::Error as ModelError;
+    use docstore_domain::ports::secondary::storage::MockDocumentStorage;
+
+    #[tokio::test]
+    async fn should_not_be_ready_when_the_storage_fails() {
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_health_check()
+            .returning(|_| Err(ModelError::DeadlineExceeded));
+        let report = ready(&storage, Duration::from_millis(100)).await;
+        assert_eq!(report.status, Status::Down);
+        assert_eq!(report.checks["postgresql"].status, Status::Down);
+        assert_eq!(
+            report.into_response().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_report_the_details_of_storage_errors() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_health_check().returning(|_| {
+            Err(ModelError::Storage {
+                source: "password authentication failed for user \"bob\"".into(),
+            })
+        });
+        let report = ready(&storage, Duration::from_millis(100)).await;
+        assert_eq!(
+            report.checks["postgresql"].error.as_deref(),
+            Some("Internal Server Error")
+        );
+    }
+}
@@ -1,8 +1,20 @@
 use clap::Parser;
 use snafu::{ResultExt, Snafu};
 
+mod auth;
+mod compression;
+mod context;
+mod cors;
+mod feed;
+mod health;
+mod metrics;
+mod rate_limit;
 mod server;
 mod settings;
+mod site;
+mod telemetry;
+mod tls;
+mod transfer;
 mod utils;
 
 #[derive(Debug, Snafu)]
@@ -16,6 +28,10 @@ pub enum Error {
         #[snafu(backtrace)]
         source: server::Error,
     },
+    #[snafu(display("Transfer Error: {}", source))]
+    TransferError { source: transfer::Error },
+    #[snafu(display("Site Error: {}", source))]
+    SiteError { source: site::Error },
 }
 
 #[tokio::main]
@@ -24,5 +40,14 @@ async fn main() -> Result<(), Error> {
     match opts.cmd {
         settings::Command::Run => server::run(&opts).await.context(ServerError),
         settings::Command::Config => server::config(&opts).await.context(ServerError),
+        settings::Command::Export(ref export) => {
+            transfer::export(&opts, export).await.context(TransferError)
+        }
+        settings::Command::Import(ref import) => {
+            transfer::import(&opts, import).await.context(TransferError)
+        }
+        settings::Command::ExportSite(ref export) => {
+            site::export_site(&opts, export).await.context(SiteError)
+        }
     }
 }
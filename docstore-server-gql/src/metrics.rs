@@ -0,0 +1,202 @@
+use chrono::Utc;
+use docstore_adapter_1ry_gql::metrics::ResolverObserver;
+use docstore_adapter_2ry_pg::PostgresqlStorage;
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::ports::secondary::storage::DocumentStorage;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::settings::MetricsConfig;
+
+/// The metrics exposed at '/metrics', in the Prometheus text format.
+pub struct Metrics {
+    registry: Registry,
+    // Paths reported as they are, the others are reported as 'other', so that random
+    // paths do not create new series.
+    routes: HashSet<String>,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    resolver_duration: HistogramVec,
+    pool_size: IntGauge,
+    pool_idle: IntGauge,
+    pool_waiting: IntGauge,
+    documents: IntGaugeVec,
+}
+
+impl Metrics {
+    /// `routes` are the paths served by the server, with '{id}' in place of ids.
+    pub fn new(routes: Vec<String>) -> Result<Self, prometheus::Error> {
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of HTTP requests"),
+            &["method", "route", "status"],
+        )?;
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to serve HTTP requests",
+            ),
+            &["method", "route"],
+        )?;
+        let resolver_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "graphql_resolver_duration_seconds",
+                "Time taken by the resolvers of the GraphQL operations",
+            ),
+            &["type", "field", "outcome"],
+        )?;
+        let pool_size = IntGauge::new(
+            "postgresql_pool_connections",
+            "Number of connections in the pool, idle or in use",
+        )?;
+        let pool_idle = IntGauge::new(
+            "postgresql_pool_idle_connections",
+            "Number of idle connections in the pool",
+        )?;
+        let pool_waiting = IntGauge::new(
+            "postgresql_pool_waiting_requests",
+            "Number of requests waiting for a connection",
+        )?;
+        let documents = IntGaugeVec::new(
+            Opts::new("docstore_documents", "Number of documents"),
+            &["genre"],
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_duration.clone()))?;
+        registry.register(Box::new(resolver_duration.clone()))?;
+        registry.register(Box::new(pool_size.clone()))?;
+        registry.register(Box::new(pool_idle.clone()))?;
+        registry.register(Box::new(pool_waiting.clone()))?;
+        registry.register(Box::new(documents.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            routes: routes.into_iter().collect(),
+            http_requests,
+            http_duration,
+            resolver_duration,
+            pool_size,
+            pool_idle,
+            pool_waiting,
+            documents,
+        })
+    }
+
+    /// Records a request served by warp (see `warp::log::custom`).
+    pub fn observe_request(&self, info: &warp::log::Info) {
+        let method = info.method().as_str();
+        let route = self.route(info.path());
+        self.http_requests
+            .with_label_values(&[method, &route, info.status().as_str()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[method, &route])
+            .observe(info.elapsed().as_secs_f64());
+    }
+
+    fn route(&self, path: &str) -> String {
+        let route = path
+            .split('/')
+            .map(|segment| match Uuid::parse_str(segment) {
+                Ok(_) => "{id}",
+                Err(_) => segment,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if self.routes.contains(&route) {
+            route
+        } else {
+            String::from("other")
+        }
+    }
+
+    /// Refreshes the gauges of the pool, and returns every metric in the Prometheus text
+    /// format. The document counts are refreshed in the background (see
+    /// `refresh_documents`), since counting on every scrape would be too costly.
+    pub fn render(&self, storage: &PostgresqlStorage) -> String {
+        let status = storage.pool_status();
+        self.pool_size.set(i64::from(status.size));
+        self.pool_idle.set(status.idle as i64);
+        self.pool_waiting.set(status.waiting as i64);
+
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Could not encode the metrics: {}", err);
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    // The document counts keep their previous values when the storage cannot count
+    // them in time.
+    async fn count_documents(&self, storage: &PostgresqlStorage, timeout: Duration) {
+        let context = RequestContext {
+            deadline: chrono::Duration::from_std(timeout)
+                .ok()
+                .map(|timeout| Utc::now() + timeout),
+            ..RequestContext::default()
+        };
+        match tokio::time::timeout(timeout, storage.count_documents(&context)).await {
+            Ok(Ok(counts)) => {
+                self.documents.reset();
+                for (genre, count) in counts {
+                    self.documents
+                        .with_label_values(&[genre.as_str()])
+                        .set(count as i64);
+                }
+            }
+            Ok(Err(err)) => tracing::warn!("Could not count the documents: {}", err),
+            Err(_) => tracing::warn!("Could not count the documents in time"),
+        }
+    }
+}
+
+/// Counts the documents at the interval given by the settings, until the task is
+/// dropped.
+pub async fn refresh_documents(
+    metrics: Arc<Metrics>,
+    storage: PostgresqlStorage,
+    config: MetricsConfig,
+) {
+    let timeout = Duration::from_millis(config.refresh_timeout);
+    let mut interval = tokio::time::interval(Duration::from_millis(config.refresh_interval));
+    loop {
+        interval.tick().await;
+        metrics.count_documents(&storage, timeout).await;
+    }
+}
+
+impl ResolverObserver for Metrics {
+    fn observe(&self, parent_type: &str, field: &str, duration: Duration, success: bool) {
+        let outcome = if success { "success" } else { "error" };
+        self.resolver_duration
+            .with_label_values(&[parent_type, field, outcome])
+            .observe(duration.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_unknown_paths_as_other() {
+        let metrics = Metrics::new(vec![
+            String::from("/"),
+            String::from("/api/v1/documents/{id}"),
+        ])
+        .unwrap();
+        assert_eq!(metrics.route("/"), "/");
+        assert_eq!(
+            metrics.route("/api/v1/documents/3f2504e0-4f89-11d3-9a0c-0305e82c3301"),
+            "/api/v1/documents/{id}"
+        );
+        assert_eq!(metrics.route("/wp-admin.php"), "other");
+    }
+}
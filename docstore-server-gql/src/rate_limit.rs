@@ -0,0 +1,362 @@
+use async_graphql::parser::parse_query;
+use async_graphql::parser::types::{DocumentOperations, OperationType};
+use async_graphql::{ErrorExtensionValues, ServerError};
+use docstore_domain::model::context::RequestContext;
+use http::HeaderMap;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use warp::Rejection;
+
+use super::context::ContextBuilder;
+use super::settings::{Budget, RateLimit};
+
+// Above this number of buckets, the full ones are dropped: they are in the same state
+// as a new bucket.
+const MAX_BUCKETS: usize = 10_000;
+
+// Minimum time between two sweeps of the buckets, so that a server with many clients
+// does not go through all their buckets on every request.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Query,
+    Mutation,
+}
+
+/// The operation of a GraphQL request. Requests which cannot be parsed, like
+/// persisted queries sent by hash, count as mutations: they may be ones, and the
+/// budget of the mutations is the smaller one.
+pub fn operation(request: &async_graphql::Request) -> Operation {
+    let document = match parse_query(&request.query) {
+        Ok(document) => document,
+        Err(_) => return Operation::Mutation,
+    };
+    let is_mutation = match &document.operations {
+        DocumentOperations::Single(operation) => operation.node.ty == OperationType::Mutation,
+        DocumentOperations::Multiple(operations) => match &request.operation_name {
+            Some(name) => operations.get(name.as_str()).map_or(false, |operation| {
+                operation.node.ty == OperationType::Mutation
+            }),
+            None => operations
+                .values()
+                .any(|operation| operation.node.ty == OperationType::Mutation),
+        },
+    };
+    if is_mutation {
+        Operation::Mutation
+    } else {
+        Operation::Query
+    }
+}
+
+/// Identifies the client of a request: its subject when it is authenticated, or its
+/// IP address.
+pub fn client(context: &RequestContext, remote: Option<SocketAddr>) -> String {
+    match &context.principal.subject {
+        Some(subject) => format!("subject:{}", subject),
+        None => address_client(remote),
+    }
+}
+
+fn address_client(remote: Option<SocketAddr>) -> String {
+    match remote {
+        Some(remote) => format!("ip:{}", remote.ip()),
+        None => String::from("anonymous"),
+    }
+}
+
+// Identifies the client of a request by its API key, before the key is checked. The
+// key is hashed, so that the buckets do not keep secrets in memory.
+fn api_key_client(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|key| format!("key:{:x}", Sha256::digest(key.as_bytes())))
+}
+
+/// The client is over its budget.
+#[derive(Debug)]
+pub struct RateLimited {
+    /// Time after which a request will be accepted.
+    pub retry_after: Duration,
+}
+
+impl RateLimited {
+    /// The value of the 'Retry-After' header, in whole seconds.
+    pub fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs();
+        if self.retry_after.subsec_nanos() > 0 {
+            secs + 1
+        } else {
+            secs
+        }
+    }
+
+    /// The GraphQL response of a rejected request.
+    pub fn graphql_response(&self) -> async_graphql::Response {
+        let mut extensions = ErrorExtensionValues::default();
+        extensions.set("code", "RATE_LIMITED");
+        extensions.set("retryAfter", self.retry_after_secs());
+        let mut error = ServerError::new(self.to_string(), None);
+        error.extensions = Some(extensions);
+        async_graphql::Response::from_errors(vec![error])
+    }
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Too Many Requests: retry in {} seconds",
+            self.retry_after_secs()
+        )
+    }
+}
+
+impl warp::reject::Reject for RateLimited {}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, budget: &Budget, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * budget.per_second).min(f64::from(budget.burst));
+        self.updated_at = now;
+    }
+}
+
+struct Buckets {
+    buckets: HashMap<(String, Operation), Bucket>,
+    swept_at: Instant,
+}
+
+/// Token buckets, one per client and operation.
+pub struct RateLimiter {
+    config: RateLimit,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimit) -> Self {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                swept_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Builds the context of a request (see `ContextBuilder::build`) within the budget
+    /// of its client (see `check_before_authentication`).
+    pub async fn context(
+        &self,
+        builder: &ContextBuilder,
+        headers: &HeaderMap,
+        remote: Option<SocketAddr>,
+        operation: Operation,
+    ) -> Result<RequestContext, Rejection> {
+        self.check_before_authentication(headers, remote, operation)
+            .map_err(warp::reject::custom)?;
+        let context = builder.build(headers).await.map_err(warp::reject::custom)?;
+        self.check_after_authentication(headers, &context, remote, operation)
+            .map_err(warp::reject::custom)?;
+        Ok(context)
+    }
+
+    /// Checking an API key costs a round trip to the database, so requests with a key
+    /// are counted against their address before the key is checked: a client cannot
+    /// escape its budget by sending a new key with each request.
+    pub fn check_before_authentication(
+        &self,
+        headers: &HeaderMap,
+        remote: Option<SocketAddr>,
+        operation: Operation,
+    ) -> Result<(), RateLimited> {
+        match api_key_client(headers) {
+            Some(_) => self.check(&address_client(remote), operation),
+            None => Ok(()),
+        }
+    }
+
+    /// Once the key is known to be valid, requests with a key are also counted against
+    /// it. The others are counted against their subject or their address (see
+    /// `client`).
+    pub fn check_after_authentication(
+        &self,
+        headers: &HeaderMap,
+        context: &RequestContext,
+        remote: Option<SocketAddr>,
+        operation: Operation,
+    ) -> Result<(), RateLimited> {
+        match api_key_client(headers) {
+            Some(client) => self.check(&client, operation),
+            None => self.check(&client(context, remote), operation),
+        }
+    }
+
+    /// Takes a token from the bucket of the client for the operation.
+    pub fn check(&self, client: &str, operation: Operation) -> Result<(), RateLimited> {
+        self.check_at(client, operation, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        client: &str,
+        operation: Operation,
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let budget = match operation {
+            Operation::Query => self.config.queries,
+            Operation::Mutation => self.config.mutations,
+        };
+        let mut buckets = self.buckets.lock().expect("rate limiter lock");
+        if buckets.buckets.len() >= MAX_BUCKETS
+            && now.saturating_duration_since(buckets.swept_at) >= SWEEP_INTERVAL
+        {
+            buckets.swept_at = now;
+            buckets.buckets.retain(|(_, operation), bucket| {
+                let budget = match operation {
+                    Operation::Query => &self.config.queries,
+                    Operation::Mutation => &self.config.mutations,
+                };
+                bucket.refill(budget, now);
+                bucket.tokens < f64::from(budget.burst)
+            });
+        }
+        let bucket = buckets
+            .buckets
+            .entry((client.to_string(), operation))
+            .or_insert_with(|| Bucket {
+                tokens: f64::from(budget.burst),
+                updated_at: now,
+            });
+        bucket.refill(&budget, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            // Bounded, for budgets which are never refilled.
+            let secs = ((1.0 - bucket.tokens) / budget.per_second).clamp(0.0, 86400.0);
+            Err(RateLimited {
+                retry_after: Duration::from_secs_f64(secs),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_limit_each_client_and_operation() {
+        let limiter = RateLimiter::new(RateLimit {
+            enabled: true,
+            queries: Budget {
+                burst: 2,
+                per_second: 1.0,
+            },
+            mutations: Budget {
+                burst: 1,
+                per_second: 0.5,
+            },
+        });
+        let now = Instant::now();
+        assert!(limiter.check_at("alice", Operation::Query, now).is_ok());
+        assert!(limiter.check_at("alice", Operation::Query, now).is_ok());
+        let limited = limiter
+            .check_at("alice", Operation::Query, now)
+            .unwrap_err();
+        assert_eq!(limited.retry_after_secs(), 1);
+
+        // Budgets are separate for each client and each operation.
+        assert!(limiter.check_at("bob", Operation::Query, now).is_ok());
+        assert!(limiter.check_at("alice", Operation::Mutation, now).is_ok());
+        let limited = limiter
+            .check_at("alice", Operation::Mutation, now)
+            .unwrap_err();
+        assert_eq!(limited.retry_after_secs(), 2);
+
+        // Tokens are added over time.
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at("alice", Operation::Query, later).is_ok());
+    }
+
+    #[test]
+    fn should_limit_clients_rotating_their_api_keys() {
+        let limiter = RateLimiter::new(RateLimit {
+            enabled: true,
+            queries: Budget {
+                burst: 2,
+                per_second: 0.0,
+            },
+            mutations: Budget {
+                burst: 2,
+                per_second: 0.0,
+            },
+        });
+        let remote = Some(SocketAddr::from(([192, 0, 2, 1], 40000)));
+        let with_key = |key: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-api-key", http::HeaderValue::from_str(key).unwrap());
+            headers
+        };
+        for key in ["first", "second"] {
+            assert!(limiter
+                .check_before_authentication(&with_key(key), remote, Operation::Query)
+                .is_ok());
+        }
+        // A new key does not come with a new budget.
+        assert!(limiter
+            .check_before_authentication(&with_key("third"), remote, Operation::Query)
+            .is_err());
+
+        // Valid keys are also counted against themselves, wherever they come from.
+        let context = RequestContext::default();
+        let headers = with_key("first");
+        for _ in 0..2 {
+            assert!(limiter
+                .check_after_authentication(&headers, &context, None, Operation::Query)
+                .is_ok());
+        }
+        assert!(limiter
+            .check_after_authentication(&headers, &context, None, Operation::Query)
+            .is_err());
+    }
+
+    #[test]
+    fn should_count_unknown_operations_as_mutations() {
+        let classify = |query: &str| operation(&async_graphql::Request::new(query));
+        assert_eq!(classify("{ documents { id } }"), Operation::Query);
+        assert_eq!(
+            classify("mutation { deleteDocument(id: 1) }"),
+            Operation::Mutation
+        );
+        // Persisted queries sent by hash have no query.
+        assert_eq!(classify(""), Operation::Mutation);
+    }
+
+    #[test]
+    fn should_identify_clients_by_their_hashed_api_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(api_key_client(&headers), None);
+        headers.insert("x-api-key", http::HeaderValue::from_static(" secret "));
+        let client = api_key_client(&headers).unwrap();
+        assert!(client.starts_with("key:"));
+        assert!(!client.contains("secret"));
+    }
+}
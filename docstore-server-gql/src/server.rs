@@ -1,20 +1,46 @@
-use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+use async_graphql::http::{graphiql_source, playground_source, GraphQLPlaygroundConfig};
 use async_graphql_warp::{GraphQLBadRequest, GraphQLResponse};
 use docstore_adapter_1ry_gql as graphql;
+use docstore_adapter_1ry_gql::metrics::ResolverObserver;
+use docstore_adapter_1ry_grpc as grpc;
+use docstore_adapter_1ry_rest as rest;
 use docstore_adapter_2ry_pg as postgresql;
+use docstore_domain::model::authorization::RolePolicy;
+use docstore_domain::model::context::RequestContext;
 use docstore_domain::model::error::Error as ModelError;
+use docstore_domain::ports::primary::storage::DocumentStorage;
+use docstore_domain::service::authorized::Authorized;
+use docstore_domain::service::document::DocumentService;
 use http::StatusCode;
 use snafu::{ResultExt, Snafu};
 use std::convert::Infallible;
-use std::net::ToSocketAddrs;
+use std::future::Future;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
 use tracing::instrument;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::{EnvFilter, Registry};
-use warp::{http::Method, http::Response as HttpResponse, Filter, Rejection};
+use warp::filters::BoxedFilter;
+use warp::reject::{LengthRequired, PayloadTooLarge};
+use warp::{http::HeaderMap, http::Method, http::Response as HttpResponse};
+use warp::{Filter, Rejection, Reply};
 
-use super::settings::{Error as SettingsError, Opts, Settings};
+use super::auth::{self, AuthRejection, Authenticator};
+use super::compression;
+use super::context::{ContextBuilder, GrpcContextProvider};
+use super::cors::{self, CorsPolicy};
+use super::feed::{self, FeedFormat, FeedQuery};
+use super::health;
+use super::metrics::{self, Metrics};
+use super::rate_limit::{self, Operation, RateLimited, RateLimiter};
+use super::settings::{Error as SettingsError, Ide, IdeKind, Opts, Settings};
+use super::telemetry;
+use super::tls;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -24,6 +50,9 @@ pub enum Error {
     #[snafu(display("Model Error: {}", source))]
     Model { source: ModelError },
 
+    #[snafu(display("Schema Error: {}", source))]
+    Schema { source: graphql::api::Error },
+
     #[snafu(display("Could not generate settings: {}", source))]
     SettingsProcessing { source: SettingsError },
 
@@ -39,6 +68,29 @@ pub enum Error {
 
     #[snafu(display("Could not init log file: {}", source))]
     InitLog { source: std::io::Error },
+
+    #[snafu(display("Could not init trace export: {}", source))]
+    InitTelemetry {
+        source: opentelemetry::trace::TraceError,
+    },
+
+    #[snafu(display("Authentication Error: {}", source))]
+    Authentication { source: auth::Error },
+
+    #[snafu(display("Metrics Error: {}", source))]
+    Metrics { source: prometheus::Error },
+
+    #[snafu(display("Could not bind the server: {}", source))]
+    Bind { source: warp::Error },
+
+    #[snafu(display("CORS Error: {}", source))]
+    Cors { source: cors::Error },
+
+    #[snafu(display("TLS Error: {}", source))]
+    Tls { source: tls::Error },
+
+    #[snafu(display("gRPC Error: {}", source))]
+    Grpc { source: tonic::transport::Error },
 }
 
 #[allow(clippy::needless_lifetimes)]
@@ -46,12 +98,17 @@ pub async fn run(opts: &Opts) -> Result<(), Error> {
     let settings = Settings::new(opts).context(SettingsProcessing)?;
     LogTracer::init().expect("Unable to setup log tracer!");
 
-    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "tracing=info,gql=info".to_owned());
+    // The filter applies to the spans too: the request spans and the sql spans must be
+    // enabled for the traces. Targets are matched by prefix, 'docstore' covers the
+    // targets of every crate of the workspace, eg 'docstore_adapter_2ry_pg'.
+    let filter = std::env::var("RUST_LOG")
+        .unwrap_or_else(|_| "tracing=info,gql=info,docstore=info".to_owned());
 
     // following code mostly from https://betterprogramming.pub/production-grade-logging-in-rust-applications-2c7fffd108a6
     let app_name = concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION")).to_string();
 
     // tracing_appender::non_blocking()
+    // The guard flushes the logs when it is dropped, on return.
     let (non_blocking, _guard) = {
         if settings.logging.path.is_dir() {
             let file_appender = tracing_appender::rolling::daily(&settings.logging.path, "gql.log");
@@ -69,13 +126,19 @@ pub async fn run(opts: &Opts) -> Result<(), Error> {
     };
 
     let bunyan_formatting_layer = BunyanFormattingLayer::new(app_name, non_blocking);
+    let telemetry_layer = telemetry::tracer(settings.logging.otlp.as_ref())
+        .context(InitTelemetry)?
+        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
     let subscriber = Registry::default()
         .with(EnvFilter::new(&filter))
         .with(JsonStorageLayer)
-        .with(bunyan_formatting_layer);
+        .with(bunyan_formatting_layer)
+        .with(telemetry_layer);
     tracing::subscriber::set_global_default(subscriber).expect("tracing subscriber global default");
 
-    run_server(settings).await
+    let result = run_server(settings).await;
+    telemetry::shutdown();
+    result
 }
 
 #[allow(clippy::needless_lifetimes)]
@@ -91,50 +154,380 @@ pub async fn run_server(settings: Settings) -> Result<(), Error> {
         .await
         .context(Store)?;
 
-    let service = Box::new(store);
+    let policy = RolePolicy::new(settings.authorization.policy.clone());
 
-    let schema = graphql::api::schema(service);
+    // Every service publishes its events to the gRPC 'WatchDocuments' streams.
+    let events = Arc::new(grpc::events::BroadcastPublisher::new(
+        settings.grpc.watch_capacity,
+    ));
 
-    let graphql_post = async_graphql_warp::graphql(schema).and_then(
-        |(schema, request): (graphql::api::DocStoreSchema, async_graphql::Request)| async move {
-            // let request_id = Uuid::new_v4();
-            // let root_span = span!(parent: None, Level::INFO, "graphql request", %request_id);
-            // let request = request.data(Tracing::default().parent_span(root_span));
-            Ok::<_, Infallible>(GraphQLResponse::from(schema.execute(request).await))
-        },
+    let rest_base = settings.rest.base_path();
+    let metrics = Arc::new(
+        Metrics::new(vec![
+            // The GraphQL endpoint.
+            String::from("/"),
+            settings.ide.mount_path(),
+            String::from("/schema.graphql"),
+            String::from("/health/live"),
+            String::from("/health/ready"),
+            String::from("/metrics"),
+            String::from("/feed.atom"),
+            String::from("/feed.rss"),
+            format!("{}/documents", rest_base),
+            format!("{}/documents/{{id}}", rest_base),
+            format!("{}/openapi.json", rest_base),
+        ])
+        .context(Metrics)?,
     );
 
-    let graphql_playground = warp::path::end().and(warp::get()).map(|| {
-        HttpResponse::builder()
-            .header("content-type", "text/html")
-            .body(playground_source(GraphQLPlaygroundConfig::new("/")))
-    });
+    let service = Box::new(
+        DocumentService::new(Box::new(store.clone()), Box::new(policy.clone()))
+            .with_validation(settings.validation.clone())
+            .with_event_publisher(events.clone()),
+    );
+    let api_keys = Box::new(Authorized::new(store.clone(), Box::new(policy.clone())));
+
+    let schema = graphql::api::schema(
+        service,
+        api_keys,
+        Arc::new(policy.clone()),
+        Some(metrics.clone() as Arc<dyn ResolverObserver>),
+        &settings.graphql,
+    )
+    .context(Schema)?;
+    // The SDL reveals the whole schema, like introspection.
+    let sdl = if settings.graphql.disable_introspection {
+        None
+    } else {
+        Some(schema.sdl())
+    };
+
+    // The feeds, the REST API, and the gRPC API are served outside of GraphQL, so
+    // they share their own service.
+    let documents: Arc<dyn DocumentStorage + Send + Sync> = Arc::new(
+        DocumentService::new(Box::new(store.clone()), Box::new(policy.clone()))
+            .with_validation(settings.validation.clone())
+            .with_event_publisher(events.clone()),
+    );
+    let feed_config = Arc::new(settings.feed.clone());
+
+    let health_store = store.clone();
+    let metrics_store = store.clone();
+    let documents_count = tokio::spawn(metrics::refresh_documents(
+        metrics.clone(),
+        store.clone(),
+        settings.metrics.clone(),
+    ));
+    let api_key_authenticator = Arc::new(Authorized::new(store.clone(), Box::new(policy.clone())));
+
+    let authenticator = Arc::new(Authenticator::new(&settings.auth).context(Authentication)?);
+    let context_builder = ContextBuilder {
+        authenticator,
+        api_keys: api_key_authenticator,
+        authorization: Arc::new(settings.authorization.clone()),
+        request_timeout: settings.service.request_timeout,
+    };
+
+    let rate_limiter = Arc::new(RateLimiter::new(settings.rate_limit.clone()));
+    // The context of the routes other than GraphQL, whose method tells the operation.
+    let limited_context = {
+        let rate_limiter = rate_limiter.clone();
+        let context_builder = context_builder.clone();
+        warp::header::headers_cloned()
+            .and(tls::remote())
+            .and(warp::method())
+            .and_then(
+                move |headers: HeaderMap, remote: Option<SocketAddr>, method: Method| {
+                    let rate_limiter = rate_limiter.clone();
+                    let context_builder = context_builder.clone();
+                    let operation = if method == Method::GET || method == Method::HEAD {
+                        Operation::Query
+                    } else {
+                        Operation::Mutation
+                    };
+                    async move {
+                        rate_limiter
+                            .context(&context_builder, &headers, remote, operation)
+                            .await
+                    }
+                },
+            )
+    };
+
+    // GET requests carry the query in their URL, only the bodies of POST requests
+    // are limited.
+    let graphql_body_limit = warp::post()
+        .and(warp::body::content_length_limit(
+            settings.service.content_length_limit,
+        ))
+        .or(warp::get())
+        .unify();
+    let graphql_context_builder = context_builder.clone();
+    let bulk_context_builder = context_builder.clone();
+    let bulk_rate_limiter = rate_limiter.clone();
+    let grpc_rate_limiter = rate_limiter.clone();
+    let graphql_post = graphql_body_limit
+        .and(async_graphql_warp::graphql(schema.clone()))
+        .and(warp::header::headers_cloned())
+        .and(tls::remote())
+        .and_then(
+            move |(schema, request): (graphql::api::DocStoreSchema, async_graphql::Request),
+                  headers: HeaderMap,
+                  remote: Option<SocketAddr>| {
+                let rate_limiter = rate_limiter.clone();
+                let context_builder = graphql_context_builder.clone();
+                async move {
+                    let operation = rate_limit::operation(&request);
+                    let context = match rate_limiter
+                        .context(&context_builder, &headers, remote, operation)
+                        .await
+                    {
+                        Ok(context) => context,
+                        // Rejected with a GraphQL response, which GraphQL clients can read.
+                        Err(rejection) => match rejection.find::<RateLimited>() {
+                            Some(limited) => {
+                                return Ok(warp::reply::with_header(
+                                    warp::reply::with_status(
+                                        GraphQLResponse::from(limited.graphql_response()),
+                                        StatusCode::TOO_MANY_REQUESTS,
+                                    ),
+                                    "retry-after",
+                                    limited.retry_after_secs(),
+                                )
+                                .into_response())
+                            }
+                            None => return Err(rejection),
+                        },
+                    };
+                    let request = request.data(context);
+                    Ok(GraphQLResponse::from(schema.execute(request).await).into_response())
+                }
+            },
+        );
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_methods(&[Method::GET, Method::POST])
-        .allow_headers(vec!["content-type"]);
+    // Larger bodies, eg. of 'addDocuments', are only read once the caller is
+    // authenticated, and counted as mutations. The smaller ones were served, or
+    // rejected for another reason, by 'graphql_post'.
+    let content_length_limit = settings.service.content_length_limit;
+    let graphql_bulk = warp::post()
+        .and(warp::header::<u64>("content-length"))
+        .and(warp::header::headers_cloned())
+        .and(tls::remote())
+        .and_then(
+            move |length: u64, headers: HeaderMap, remote: Option<SocketAddr>| {
+                let rate_limiter = bulk_rate_limiter.clone();
+                let context_builder = bulk_context_builder.clone();
+                async move {
+                    if length <= content_length_limit {
+                        return Err(warp::reject::not_found());
+                    }
+                    let context = rate_limiter
+                        .context(&context_builder, &headers, remote, Operation::Mutation)
+                        .await?;
+                    // Anonymous callers got a 413 from 'graphql_post'.
+                    if context.principal.subject.is_none() {
+                        return Err(warp::reject::not_found());
+                    }
+                    Ok(context)
+                }
+            },
+        )
+        .and(warp::body::content_length_limit(
+            settings.service.bulk_content_length_limit,
+        ))
+        .and(async_graphql_warp::graphql(schema))
+        .and_then(
+            |context: RequestContext,
+             (schema, request): (graphql::api::DocStoreSchema, async_graphql::Request)| async move {
+                let request = request.data(context);
+                Ok::<_, Infallible>(
+                    GraphQLResponse::from(schema.execute(request).await).into_response(),
+                )
+            },
+        );
+
+    let graphql_ide = ide(&settings.ide);
+
+    let graphql_sdl = warp::path!("schema.graphql")
+        .and(warp::get())
+        .and_then(move || {
+            let sdl = sdl.clone();
+            async move {
+                sdl.map(|sdl| {
+                    HttpResponse::builder()
+                        .header("content-type", "text/plain; charset=utf-8")
+                        .body(sdl)
+                })
+                .ok_or_else(warp::reject::not_found)
+            }
+        });
+
+    let health_check_timeout = Duration::from_millis(settings.service.health_check_timeout);
+    let live = warp::path!("health" / "live")
+        .and(warp::get())
+        .map(health::live);
+    let ready = warp::path!("health" / "ready")
+        .and(warp::get())
+        .and_then(move || {
+            let store = health_store.clone();
+            async move { Ok::<_, Infallible>(health::ready(&store, health_check_timeout).await) }
+        });
+
+    let metrics_route = {
+        let metrics = metrics.clone();
+        warp::path!("metrics").and(warp::get()).map(move || {
+            warp::reply::with_header(
+                metrics.render(&metrics_store),
+                "content-type",
+                "text/plain; version=0.0.4",
+            )
+        })
+    };
+
+    let rest = rest::api::routes(
+        documents.clone(),
+        &settings.rest,
+        limited_context.clone(),
+        settings.service.content_length_limit,
+    );
+
+    let feed_documents = documents.clone();
+    let feeds = warp::get()
+        .and(
+            warp::path("feed.atom")
+                .map(|| FeedFormat::Atom)
+                .or(warp::path("feed.rss").map(|| FeedFormat::Rss))
+                .unify(),
+        )
+        .and(warp::path::end())
+        .and(warp::query::<FeedQuery>())
+        .and(warp::header::headers_cloned())
+        .and(limited_context)
+        .and_then(
+            move |format: FeedFormat,
+                  query: FeedQuery,
+                  headers: HeaderMap,
+                  context: RequestContext| {
+                let service = feed_documents.clone();
+                let config = feed_config.clone();
+                async move {
+                    let response = feed::feed(
+                        service.as_ref(),
+                        &config,
+                        format,
+                        &query,
+                        &headers,
+                        &context,
+                    )
+                    .await;
+                    Ok::<_, Infallible>(response)
+                }
+            },
+        );
+
+    let cors = Arc::new(CorsPolicy::new(&settings.service.cors).context(Cors)?);
 
     let log = warp::log("backend");
+    let trace = warp::trace(|info| {
+        telemetry::request_span(info.method(), info.path(), info.request_headers())
+    });
+    let observe = warp::log::custom(move |info| metrics.observe_request(&info));
 
-    let routes = graphql_playground
-        .or(graphql_post)
-        .with(cors)
+    let routes = cors::preflight(cors.clone())
+        .or(cors::guard(cors.clone()).and(
+            graphql_ide
+                .or(graphql_sdl)
+                .or(live)
+                .or(ready)
+                .or(metrics_route)
+                .or(feeds)
+                .or(rest)
+                .or(graphql_post)
+                .or(graphql_bulk),
+        ))
         .with(log)
         .recover(|err: Rejection| async move {
+            // First, since the request may also be rejected by the routes tried next.
+            if let Some(limited) = err.find::<RateLimited>() {
+                return Ok::<_, Infallible>(
+                    warp::reply::with_header(
+                        warp::reply::with_status(
+                            limited.to_string(),
+                            StatusCode::TOO_MANY_REQUESTS,
+                        ),
+                        "retry-after",
+                        limited.retry_after_secs(),
+                    )
+                    .into_response(),
+                );
+            }
+
+            if err.find::<cors::Forbidden>().is_some() {
+                return Ok(warp::reply::with_status(
+                    "CORS request forbidden".to_string(),
+                    StatusCode::FORBIDDEN,
+                )
+                .into_response());
+            }
+
             if let Some(GraphQLBadRequest(err)) = err.find() {
-                return Ok::<_, Infallible>(warp::reply::with_status(
+                return Ok(
+                    warp::reply::with_status(err.to_string(), StatusCode::BAD_REQUEST)
+                        .into_response(),
+                );
+            }
+
+            if let Some(err) = err.find::<PayloadTooLarge>() {
+                return Ok(warp::reply::with_status(
                     err.to_string(),
-                    StatusCode::BAD_REQUEST,
-                ));
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                )
+                .into_response());
+            }
+
+            if let Some(err) = err.find::<LengthRequired>() {
+                return Ok(
+                    warp::reply::with_status(err.to_string(), StatusCode::LENGTH_REQUIRED)
+                        .into_response(),
+                );
+            }
+
+            match err.find() {
+                Some(AuthRejection::Unauthorized { msg }) => {
+                    return Ok(warp::reply::with_header(
+                        warp::reply::with_status(msg.to_string(), StatusCode::UNAUTHORIZED),
+                        "www-authenticate",
+                        "Bearer",
+                    )
+                    .into_response());
+                }
+                Some(AuthRejection::Unavailable) => {
+                    return Ok(warp::reply::with_status(
+                        "SERVICE_UNAVAILABLE".to_string(),
+                        StatusCode::SERVICE_UNAVAILABLE,
+                    )
+                    .into_response());
+                }
+                None => {}
             }
 
             Ok(warp::reply::with_status(
                 "INTERNAL_SERVER_ERROR".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+            )
+            .into_response())
         });
 
+    // After the recovery, so that errors are compressed and readable by browsers, and
+    // rejected requests are counted with their status.
+    let routes = warp::header::headers_cloned()
+        .and(routes)
+        .map(move |headers: HeaderMap, reply| {
+            compression::compress(&headers, cors.apply(&headers, reply))
+        })
+        .with(observe)
+        .with(trace);
+
     let host = settings.service.host;
     let port = settings.service.port;
     let addr = (host.as_str(), port);
@@ -146,7 +539,209 @@ pub async fn run_server(settings: Settings) -> Result<(), Error> {
             msg: String::from("Cannot resolve bragi addr."),
         })?;
 
-    warp::serve(routes).run(addr).await;
+    // The servers stop accepting connections when the shutdown starts, and complete
+    // once the connections in progress are closed.
+    let (shutdown, draining) = watch::channel(false);
+    // The gRPC server shares the certificates, and their reloading, with the HTTP one.
+    let acceptor = match &settings.service.tls {
+        Some(config) => {
+            let acceptor = tls::Acceptor::new(config).context(Tls)?;
+            tokio::spawn(tls::watch(acceptor.clone(), config.clone()));
+            Some(acceptor)
+        }
+        None => None,
+    };
+    let http: Pin<Box<dyn Future<Output = ()> + Send>> = match &acceptor {
+        Some(acceptor) => {
+            let incoming = tls::incoming(addr, acceptor.clone()).await.context(Tls)?;
+            Box::pin(tls::serve(
+                warp::service(routes),
+                incoming,
+                drained(draining.clone()),
+            ))
+        }
+        None => {
+            let (_, http) = warp::serve(routes)
+                .try_bind_with_graceful_shutdown(addr, drained(draining.clone()))
+                .context(Bind)?;
+            Box::pin(http)
+        }
+    };
+
+    let grpc = if settings.grpc.enabled {
+        let grpc_addr = SocketAddr::new(addr.ip(), settings.grpc.port);
+        let grpc_service = grpc::service::DocumentsService::new(
+            documents,
+            Arc::new(policy),
+            events,
+            Arc::new(GrpcContextProvider {
+                builder: context_builder,
+                rate_limiter: grpc_rate_limiter,
+            }),
+            &settings.grpc,
+        );
+        let router = tonic::transport::Server::builder()
+            .trace_fn(|request: &http::Request<()>| {
+                telemetry::request_span(request.method(), request.uri().path(), request.headers())
+            })
+            .add_service(grpc_service.into_server());
+        let grpc: Pin<Box<dyn Future<Output = Result<(), tonic::transport::Error>> + Send>> =
+            match acceptor {
+                Some(acceptor) => {
+                    let incoming = tls::incoming(grpc_addr, acceptor).await.context(Tls)?;
+                    Box::pin(router.serve_with_incoming_shutdown(incoming, drained(draining)))
+                }
+                None => Box::pin(router.serve_with_shutdown(grpc_addr, drained(draining))),
+            };
+        Some(grpc)
+    } else {
+        None
+    };
 
-    Ok(())
+    let servers = async move {
+        match grpc {
+            // A gRPC failure, eg. when its port cannot be bound, stops the HTTP server
+            // too, rather than leaving a half-working process.
+            Some(grpc) => tokio::try_join!(
+                async {
+                    http.await;
+                    Ok::<_, Error>(())
+                },
+                async { grpc.await.context(Grpc) },
+            )
+            .map(|((), ())| ()),
+            None => {
+                http.await;
+                Ok(())
+            }
+        }
+    };
+    tokio::pin!(servers);
+
+    let drain_timeout = Duration::from_millis(settings.service.drain_timeout);
+    let result = tokio::select! {
+        result = &mut servers => result,
+        () = shutdown_signal() => {
+            tracing::info!(
+                "Shutting down, waiting up to {}ms for the requests in progress",
+                drain_timeout.as_millis()
+            );
+            let _ = shutdown.send(true);
+            match tokio::time::timeout(drain_timeout, &mut servers).await {
+                Ok(result) => result,
+                Err(_) => {
+                    // Dropping the servers closes the remaining connections, eg. the
+                    // 'WatchDocuments' streams.
+                    tracing::warn!(
+                        "Requests still in progress after {}ms are dropped",
+                        drain_timeout.as_millis()
+                    );
+                    Ok(())
+                }
+            }
+        }
+    };
+
+    documents_count.abort();
+    store.close().await;
+    result
+}
+
+// Serves the page of the IDE at its path. The IDE sends its requests to '/', like
+// the other clients.
+fn ide(config: &Ide) -> BoxedFilter<(warp::reply::Html<String>,)> {
+    let page = match config.kind {
+        IdeKind::Playground => Some(playground_source(GraphQLPlaygroundConfig::new("/"))),
+        IdeKind::Graphiql => Some(graphiql_source("/", None)),
+        IdeKind::None => None,
+    };
+    mount(&config.segments())
+        .and(warp::get())
+        .and_then(move || {
+            let page = page.clone();
+            async move {
+                page.map(warp::reply::html)
+                    .ok_or_else(warp::reject::not_found)
+            }
+        })
+        .boxed()
+}
+
+// Matches the path made of `segments`, and nothing below it.
+fn mount(segments: &[String]) -> BoxedFilter<()> {
+    segments
+        .iter()
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment.clone())).boxed()
+        })
+        .and(warp::path::end())
+        .boxed()
+}
+
+// Resolves when the server is asked to stop, with Ctrl-C or SIGTERM (sent by
+// orchestrators and 'docker stop').
+async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Ctrl-C handler installation");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("SIGTERM handler installation")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => {},
+        () = terminate => {},
+    }
+}
+
+// Resolves when the shutdown starts.
+async fn drained(mut draining: watch::Receiver<bool>) {
+    // An error means the sender is gone, which only happens on shutdown too.
+    let _ = draining.changed().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_mount_the_ide_at_its_path() {
+        let root = mount(&[]);
+        assert!(warp::test::request().path("/").matches(&root).await);
+        assert!(!warp::test::request().path("/ide").matches(&root).await);
+
+        let ide = mount(&[String::from("ide")]);
+        assert!(warp::test::request().path("/ide").matches(&ide).await);
+        assert!(warp::test::request().path("/ide/").matches(&ide).await);
+        assert!(!warp::test::request().path("/").matches(&ide).await);
+        assert!(!warp::test::request().path("/ide/other").matches(&ide).await);
+    }
+
+    #[tokio::test]
+    async fn should_serve_the_ide_unless_disabled() {
+        let mut config = Ide {
+            kind: IdeKind::Graphiql,
+            path: String::from("/ide/"),
+        };
+        let response = warp::test::request()
+            .path("/ide")
+            .reply(&ide(&config))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        config.kind = IdeKind::None;
+        let response = warp::test::request()
+            .path("/ide")
+            .reply(&ide(&config))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
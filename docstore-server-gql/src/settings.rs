@@ -1,9 +1,16 @@
+use jsonwebtoken::Algorithm;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::env;
 use std::path::PathBuf;
 
+use docstore_adapter_1ry_gql::GraphqlConfig;
+use docstore_adapter_1ry_grpc::GrpcConfig;
+use docstore_adapter_1ry_rest::RestConfig;
 use docstore_adapter_2ry_pg::PostgresqlStorageConfig;
+use docstore_domain::model::authorization::RolePolicyConfig;
+use docstore_domain::model::document::ConflictPolicy;
+use docstore_domain::model::validation::ValidationConfig;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
@@ -35,11 +42,37 @@ pub enum Error {
 
     #[snafu(display("Config Compilation Error: {}", source))]
     ConfigCompilation { source: crate::utils::config::Error },
+
+    #[snafu(display("Invalid Settings: {}", msg))]
+    InvalidSettings { msg: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Logging {
     pub path: PathBuf,
+    /// Export the traces with OTLP, when present.
+    #[serde(default)]
+    pub otlp: Option<Otlp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Otlp {
+    /// gRPC endpoint of the collector. Example: 'http://localhost:4317'
+    pub endpoint: String,
+    /// Name of the service in the traces.
+    #[serde(default = "default_otlp_service")]
+    pub service: String,
+    /// Maximum time allowed to export a batch of spans (in milliseconds).
+    #[serde(default = "default_otlp_timeout")]
+    pub timeout: u64,
+}
+
+fn default_otlp_service() -> String {
+    String::from("docstore")
+}
+
+fn default_otlp_timeout() -> u64 {
+    10000
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,8 +81,229 @@ pub struct Service {
     pub host: String,
     /// Port on which we expose gql.
     pub port: u16,
-    /// Used on POST request to set an upper limit on the size of the body (in bytes)
+    /// Used on POST request to set an upper limit on the size of the body (in bytes).
+    /// It must leave room for the largest document accepted by the validation.
     pub content_length_limit: u64,
+    /// Upper limit on the size of the body of the GraphQL requests of authenticated
+    /// callers, eg. to add documents in bulk (in bytes). Bodies above
+    /// `content_length_limit` are only read once the caller is authenticated. It must
+    /// leave room for the largest bulk request accepted by the validation.
+    pub bulk_content_length_limit: u64,
+    /// Maximum time allowed to serve a request (in milliseconds). Clients can ask
+    /// for a shorter time with the 'X-Request-Timeout' header.
+    pub request_timeout: u64,
+    /// Maximum time allowed to each check of '/health/ready' (in milliseconds).
+    pub health_check_timeout: u64,
+    /// Maximum time given to the requests in progress to complete on shutdown
+    /// (in milliseconds).
+    pub drain_timeout: u64,
+    pub cors: Cors,
+    /// Serve HTTPS rather than HTTP, when present. The gRPC API (see `grpc`) is
+    /// served over TLS too, with the same certificates.
+    #[serde(default)]
+    pub tls: Option<Tls>,
+}
+
+/// The Cross-Origin Resource Sharing policy, which tells browsers the origins allowed
+/// to call the HTTP routes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cors {
+    /// Origins allowed to call the routes, eg. 'https://docs.example.com'. A '*' matches
+    /// any sequence of characters: 'https://*.example.com' allows the subdomains of
+    /// 'example.com', and '*' allows every origin.
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed by preflight requests.
+    pub allowed_methods: Vec<String>,
+    /// Headers allowed by preflight requests.
+    pub allowed_headers: Vec<String>,
+    /// Allow requests with credentials (cookies, TLS client certificates). The
+    /// allowed origins must then be listed: '*' is rejected.
+    pub allow_credentials: bool,
+    /// Time during which browsers may cache the answer to a preflight request
+    /// (in seconds).
+    pub max_age: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tls {
+    /// PEM file holding the certificate chain, starting with the server's certificate.
+    pub cert_path: PathBuf,
+    /// PEM file holding the private key (PKCS#8, RSA, or EC).
+    pub key_path: PathBuf,
+    /// PEM file holding the authorities of the client certificates. When present,
+    /// clients must authenticate with a certificate (mTLS).
+    pub client_ca_path: Option<PathBuf>,
+    /// Interval between the checks for changes of the files (in milliseconds), above 0.
+    /// The files are also reloaded on SIGHUP.
+    #[serde(default = "default_tls_reload_interval")]
+    pub reload_interval: u64,
+}
+
+fn default_tls_reload_interval() -> u64 {
+    60000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Auth {
+    /// Algorithm used to sign the bearer tokens, eg. 'HS256', 'RS256' or 'ES256'.
+    pub algorithm: Algorithm,
+    /// File holding the HMAC secret, or the public key (PEM). The trailing newline
+    /// of the secret is removed, its other whitespaces are kept.
+    pub key_path: Option<PathBuf>,
+    /// JWKS file holding the RSA public keys, indexed by 'kid'. The algorithm must be
+    /// one of 'RS*' or 'PS*'. It takes precedence over 'key_path'.
+    pub jwks_path: Option<PathBuf>,
+    /// If present, the tokens must have been issued by this issuer.
+    pub issuer: Option<String>,
+    /// If present, the tokens must have been issued for this audience.
+    pub audience: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Authorization {
+    /// Trust the identity found in the request headers, when there is no bearer token.
+    /// Only enable this behind a proxy which authenticates the callers.
+    pub trust_headers: bool,
+    /// Header holding the caller's identity.
+    pub subject_header: String,
+    /// Header holding the caller's roles, separated by commas.
+    pub roles_header: String,
+    /// The role -> permissions matrix.
+    pub policy: RolePolicyConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feed {
+    /// Title of the Atom and RSS feeds.
+    pub title: String,
+    /// URL at which the documents are published (see the 'export-site' command).
+    /// Entries link to '<base_url>/documents/<id>.html'.
+    pub base_url: String,
+    /// Number of documents in a feed.
+    pub size: u32,
+}
+
+/// The IDE served to explore the GraphQL API.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdeKind {
+    Playground,
+    Graphiql,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ide {
+    pub kind: IdeKind,
+    /// Path at which the IDE is served. It cannot hide the other routes, eg.
+    /// '/health' or the REST API.
+    pub path: String,
+}
+
+impl Default for Ide {
+    fn default() -> Self {
+        Ide {
+            kind: IdeKind::Playground,
+            path: String::from("/"),
+        }
+    }
+}
+
+impl Ide {
+    pub fn segments(&self) -> Vec<String> {
+        self.path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// The path, as it appears in URLs: '/' or '/<segments>'.
+    pub fn mount_path(&self) -> String {
+        format!("/{}", self.segments().join("/"))
+    }
+
+    // Whether the IDE is served at the path of another route, or below it.
+    fn overlaps(&self, path: &str) -> bool {
+        let route = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+        let segments = self.segments();
+        !route.is_empty()
+            && segments.len() >= route.len()
+            && segments
+                .iter()
+                .zip(&route)
+                .all(|(segment, route)| segment == route)
+    }
+}
+
+// The paths of the routes served next to the IDE, other than the GraphQL endpoint
+// and the REST API.
+const RESERVED_PATHS: &[&str] = &[
+    "/schema.graphql",
+    "/health",
+    "/metrics",
+    "/feed.atom",
+    "/feed.rss",
+];
+
+/// The refresh of the metrics which are too costly to compute on every scrape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Time between two counts of the documents (in milliseconds), above 0.
+    pub refresh_interval: u64,
+    /// Maximum time allowed to each count (in milliseconds).
+    pub refresh_timeout: u64,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            refresh_interval: 60000,
+            refresh_timeout: 10000,
+        }
+    }
+}
+
+/// Limits the rate of the requests of each client: the caller's API key, the subject
+/// of its token, or its IP address for anonymous callers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    pub enabled: bool,
+    /// Budget of GraphQL queries, and of the GET requests of the other routes.
+    pub queries: Budget,
+    /// Budget of GraphQL mutations, and of the other requests of the other routes.
+    pub mutations: Budget,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            enabled: true,
+            queries: Budget {
+                burst: 100,
+                per_second: 20.0,
+            },
+            mutations: Budget {
+                burst: 20,
+                per_second: 2.0,
+            },
+        }
+    }
+}
+
+/// A token bucket: each request takes a token, and tokens are added at a fixed rate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Budget {
+    /// Capacity of the bucket, ie. the number of requests allowed in a burst.
+    pub burst: u32,
+    /// Number of tokens added per second.
+    pub per_second: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +312,22 @@ pub struct Settings {
     pub logging: Logging,
     pub postgresql: PostgresqlStorageConfig,
     pub service: Service,
+    pub graphql: GraphqlConfig,
+    #[serde(default)]
+    pub ide: Ide,
+    #[serde(default)]
+    pub rest: RestConfig,
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    pub auth: Auth,
+    pub authorization: Authorization,
+    pub feed: Feed,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -99,6 +369,83 @@ pub enum Command {
     Run,
     /// Prints osm2mimir's configuration
     Config,
+    /// Exports every document to a file
+    Export(ExportOpts),
+    /// Imports the documents of a file produced by 'export'
+    Import(ImportOpts),
+    /// Renders every document as a static website
+    ExportSite(ExportSiteOpts),
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct ExportOpts {
+    /// Output file, or '-' for the standard output
+    #[clap(parse(from_os_str), short = 'f', long = "file")]
+    pub file: PathBuf,
+
+    /// 'ndjson' (one JSON document per line), or 'markdown' (a tar.gz of Markdown files)
+    #[clap(long = "format", default_value = "ndjson")]
+    pub format: ArchiveFormat,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct ImportOpts {
+    /// Input file, or '-' for the standard input
+    #[clap(parse(from_os_str), short = 'f', long = "file")]
+    pub file: PathBuf,
+
+    /// 'ndjson' (one JSON document per line), or 'markdown' (a tar.gz of Markdown files)
+    #[clap(long = "format", default_value = "ndjson")]
+    pub format: ArchiveFormat,
+
+    /// What to do with documents which already exist: 'skip', 'overwrite', or 'fail' (nothing
+    /// is imported)
+    #[clap(long = "on-conflict", default_value = "skip", parse(try_from_str = parse_conflict_policy))]
+    pub on_conflict: ConflictPolicy,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct ExportSiteOpts {
+    /// Directory in which the website is written
+    #[clap(parse(from_os_str), short = 'd', long = "output-dir")]
+    pub output: PathBuf,
+
+    /// URL at which the website is published, eg 'https://docs.example.com'
+    #[clap(long = "base-url")]
+    pub base_url: String,
+
+    /// Title of the website
+    #[clap(long = "title", default_value = "Docstore")]
+    pub title: String,
+
+    /// Directory containing the Tera templates: 'index.html', 'list.html', and
+    /// 'document.html'. Built-in templates are used by default.
+    #[clap(parse(from_os_str), long = "templates")]
+    pub templates: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Ndjson,
+    Markdown,
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<ArchiveFormat, Self::Err> {
+        match input {
+            "ndjson" => Ok(ArchiveFormat::Ndjson),
+            "markdown" => Ok(ArchiveFormat::Markdown),
+            _ => Err(format!("unknown format '{}'", input)),
+        }
+    }
+}
+
+fn parse_conflict_policy(input: &str) -> Result<ConflictPolicy, String> {
+    input
+        .parse()
+        .map_err(|_| format!("unknown conflict policy '{}'", input))
 }
 
 impl Settings {
@@ -115,12 +462,105 @@ impl Settings {
         .context(ConfigMerge {
             msg: "cannot merge bragi settings",
         })
+        .and_then(Settings::check)
+    }
+
+    // Rejects the combinations of settings which would fail every matching request.
+    fn check(self) -> Result<Self, Error> {
+        let largest_document = largest_document(&self.validation);
+        if self.service.content_length_limit < largest_document {
+            return InvalidSettings {
+                msg: format!(
+                    "service.content_length_limit ({} bytes) is below the size of the largest valid document ({} bytes, see [validation])",
+                    self.service.content_length_limit, largest_document
+                ),
+            }
+            .fail();
+        }
+        let largest_batch = largest_batch(&self.validation);
+        if self.service.bulk_content_length_limit < largest_batch {
+            return InvalidSettings {
+                msg: format!(
+                    "service.bulk_content_length_limit ({} bytes) is below the size of the largest valid bulk request ({} bytes, see [validation])",
+                    self.service.bulk_content_length_limit, largest_batch
+                ),
+            }
+            .fail();
+        }
+        let overlapped = RESERVED_PATHS
+            .iter()
+            .map(|path| path.to_string())
+            .chain(std::iter::once(self.rest.base_path()))
+            .find(|path| self.ide.overlaps(path));
+        if let Some(path) = overlapped {
+            return InvalidSettings {
+                msg: format!(
+                    "ide.path ({}) would hide the routes under '{}'",
+                    self.ide.mount_path(),
+                    path
+                ),
+            }
+            .fail();
+        }
+        let cors = &self.service.cors;
+        if cors.allow_credentials && cors.allowed_origins.iter().any(|origin| origin == "*") {
+            return InvalidSettings {
+                msg: String::from(
+                    "service.cors.allowed_origins cannot hold '*' when service.cors.allow_credentials is true: every site could send requests with the credentials of the users, list the allowed origins instead",
+                ),
+            }
+            .fail();
+        }
+        let tls = self.service.tls.as_ref();
+        if tls.map_or(false, |tls| tls.reload_interval == 0) {
+            return InvalidSettings {
+                msg: String::from("service.tls.reload_interval must be above 0"),
+            }
+            .fail();
+        }
+        if self.metrics.refresh_interval == 0 {
+            return InvalidSettings {
+                msg: String::from("metrics.refresh_interval must be above 0"),
+            }
+            .fail();
+        }
+        Ok(self)
     }
 }
 
+// The number of bytes of a character once escaped in a JSON or GraphQL string: up to
+// 6 for the control characters ('\u001f').
+const ESCAPED_CHAR_SIZE: usize = 6;
+
+// The size of the document, without its content and html: its text fields, and some
+// room for its id, its genre, and the syntax.
+fn document_overhead(validation: &ValidationConfig) -> usize {
+    let text = validation.max_title_length
+        + validation.max_abstract_length
+        + validation.max_tags * validation.max_tag_length;
+    ESCAPED_CHAR_SIZE * text + 1024
+}
+
+// The size of the body of a request adding the largest document accepted by the
+// validation, with every character escaped.
+fn largest_document(validation: &ValidationConfig) -> u64 {
+    (ESCAPED_CHAR_SIZE * 2 * validation.max_content_size + document_overhead(validation)) as u64
+}
+
+// The size of the body of the largest bulk request accepted by the validation, whose
+// contents are bounded by the size of each one, and by their total size.
+fn largest_batch(validation: &ValidationConfig) -> u64 {
+    let content_size = validation
+        .max_batch_content_size
+        .min(validation.max_batch_size * 2 * validation.max_content_size);
+    (ESCAPED_CHAR_SIZE * content_size + validation.max_batch_size * document_overhead(validation))
+        as u64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use docstore_domain::model::authorization::Permission;
 
     #[test]
     fn should_return_ok_with_default_config_dir() {
@@ -140,6 +580,137 @@ mod tests {
         assert_eq!(settings.unwrap().mode, String::from("testing"));
     }
 
+    #[test]
+    fn should_compute_the_path_of_the_ide() {
+        let ide = |path: &str| Ide {
+            kind: IdeKind::Playground,
+            path: String::from(path),
+        };
+        assert!(ide("/").segments().is_empty());
+        assert_eq!(ide("/").mount_path(), "/");
+        assert_eq!(ide("/ide/").segments(), vec![String::from("ide")]);
+        assert_eq!(ide("/ide/").mount_path(), "/ide");
+        assert_eq!(ide("ide//explorer").mount_path(), "/ide/explorer");
+    }
+
+    #[test]
+    fn should_reject_an_ide_hiding_another_route() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        for (path, valid) in [
+            ("/", true),
+            ("/ide/", true),
+            ("/healthz", true),
+            ("/schema.graphql", false),
+            ("/health", false),
+            ("/health/ready/", false),
+            ("/api/v1/documents", false),
+        ] {
+            let opts = Opts {
+                config_dir: config_dir.clone(),
+                run_mode: Some(String::from("testing")),
+                overrides: vec![format!("ide.path='{}'", path)],
+                cmd: Command::Run,
+            };
+            let settings = Settings::new(&opts);
+            assert_eq!(settings.is_ok(), valid, "ide.path = {}", path);
+        }
+    }
+
+    #[test]
+    fn should_reject_every_origin_with_credentials() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![],
+            cmd: Command::Run,
+        };
+        let mut settings = Settings::new(&opts).unwrap();
+        settings.service.cors.allowed_origins = vec![String::from("*")];
+        settings.service.cors.allow_credentials = false;
+        let mut settings = settings.check().unwrap();
+
+        settings.service.cors.allow_credentials = true;
+        let err = settings.check().unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidSettings { .. }),
+            "Expected InvalidSettings, Got: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn should_reject_a_bulk_body_limit_below_the_largest_batch() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![String::from("validation.max_batch_content_size=16777216")],
+            cmd: Command::Run,
+        };
+        let err = Settings::new(&opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidSettings { .. }),
+            "Expected InvalidSettings, Got: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn should_reject_a_zero_tls_reload_interval() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![
+                String::from("service.tls.cert_path='cert.pem'"),
+                String::from("service.tls.key_path='key.pem'"),
+                String::from("service.tls.reload_interval=0"),
+            ],
+            cmd: Command::Run,
+        };
+        let err = Settings::new(&opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidSettings { .. }),
+            "Expected InvalidSettings, Got: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn should_reject_a_zero_metrics_refresh_interval() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![String::from("metrics.refresh_interval=0")],
+            cmd: Command::Run,
+        };
+        let err = Settings::new(&opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidSettings { .. }),
+            "Expected InvalidSettings, Got: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn should_reject_a_body_limit_below_the_largest_document() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![String::from("service.content_length_limit=32768")],
+            cmd: Command::Run,
+        };
+        let err = Settings::new(&opts).unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidSettings { .. }),
+            "Expected InvalidSettings, Got: {}",
+            err
+        );
+    }
+
     #[test]
     fn should_override_postgresql_port_with_command_line() {
         let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
@@ -158,6 +729,52 @@ mod tests {
         assert_eq!(settings.unwrap().postgresql.url.port().unwrap(), 9999);
     }
 
+    #[test]
+    fn should_override_graphql_limits_with_command_line() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![
+                String::from("graphql.limits.max_depth=3"),
+                String::from("graphql.limits.field_costs.html=20"),
+            ],
+            cmd: Command::Run,
+        };
+        let settings = Settings::new(&opts);
+        assert!(
+            settings.is_ok(),
+            "Expected Ok, Got an Err: {}",
+            settings.unwrap_err().to_string()
+        );
+        let limits = settings.unwrap().graphql.limits;
+        assert_eq!(limits.max_depth, 3);
+        assert_eq!(limits.field_costs.get("html"), Some(&20));
+    }
+
+    #[test]
+    fn should_read_role_permissions_matrix() {
+        let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
+        let opts = Opts {
+            config_dir,
+            run_mode: Some(String::from("testing")),
+            overrides: vec![],
+            cmd: Command::Run,
+        };
+        let settings = Settings::new(&opts);
+        assert!(
+            settings.is_ok(),
+            "Expected Ok, Got an Err: {}",
+            settings.unwrap_err().to_string()
+        );
+        let policy = settings.unwrap().authorization.policy;
+        assert_eq!(
+            policy.roles.get("editor"),
+            Some(&vec![Permission::Read, Permission::Write])
+        );
+        assert_eq!(policy.anonymous_roles, vec![String::from("reader")]);
+    }
+
     #[test]
     fn should_override_postgresql_port_environment_variable() {
         let config_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config");
@@ -0,0 +1,393 @@
+use docstore_domain::model::document::Document;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use snafu::{ResultExt, Snafu};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use tera::Tera;
+use uuid::Uuid;
+
+use super::settings::{ExportSiteOpts, Opts};
+use super::transfer::{self, cli_context, document_service, for_each_page};
+use super::utils::xml;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Transfer Error: {}", source))]
+    Transfer { source: transfer::Error },
+
+    #[snafu(display("Template Error: {}", source))]
+    Template { source: tera::Error },
+
+    #[snafu(display("IO Error with {}: {}", path.display(), source))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("JSON Error: {}", source))]
+    Json { source: serde_json::Error },
+}
+
+// Number of documents listed on the home page.
+const RECENT_DOCUMENTS: usize = 20;
+
+// The templates used when no template directory is given.
+const TEMPLATES: [(&str, &str); 5] = [
+    ("base.html", include_str!("../templates/site/base.html")),
+    (
+        "document.html",
+        include_str!("../templates/site/document.html"),
+    ),
+    (
+        "documents.html",
+        include_str!("../templates/site/documents.html"),
+    ),
+    ("index.html", include_str!("../templates/site/index.html")),
+    ("list.html", include_str!("../templates/site/list.html")),
+];
+
+#[derive(Serialize)]
+struct Site {
+    title: String,
+    base_url: String,
+}
+
+// A link to an index page, for a genre or a tag.
+#[derive(Clone, Serialize)]
+struct Link {
+    name: String,
+    slug: String,
+    count: usize,
+}
+
+// What the templates know about a document.
+#[derive(Serialize)]
+struct Page {
+    id: Uuid,
+    title: String,
+    #[serde(rename = "abstract")]
+    outline: String,
+    html: String,
+    tags: Vec<Link>,
+    genre: String,
+    genre_slug: String,
+    author: Option<String>,
+    updated_on: String,
+    /// Path of the page, relative to the site's root.
+    path: String,
+}
+
+impl Page {
+    // The tags link to the pages named after their slug in `tag_slugs`.
+    fn new(document: &Document, tag_slugs: &HashMap<String, String>) -> Self {
+        Page {
+            id: document.id,
+            title: document.title.clone(),
+            outline: document.outline.clone(),
+            html: document.html.clone(),
+            tags: document
+                .tags
+                .iter()
+                .map(|tag| Link {
+                    name: tag.clone(),
+                    slug: tag_slugs
+                        .get(&tag.to_lowercase())
+                        .cloned()
+                        .unwrap_or_else(|| slugify(tag)),
+                    count: 0,
+                })
+                .collect(),
+            genre: document.genre.as_str().to_string(),
+            genre_slug: slugify(document.genre.as_str()),
+            author: document.created_by.clone(),
+            updated_on: document.updated_at.format("%Y-%m-%d").to_string(),
+            path: format!("documents/{}.html", document.id),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    id: Uuid,
+    title: &'a str,
+    #[serde(rename = "abstract")]
+    outline: &'a str,
+    tags: &'a [String],
+    genre: &'static str,
+    url: String,
+}
+
+/// Renders every document, the index pages, a sitemap, and a search index in the
+/// output directory.
+pub async fn export_site(opts: &Opts, export: &ExportSiteOpts) -> Result<(), Error> {
+    let service = document_service(opts).await.context(Transfer)?;
+    let mut documents = Vec::new();
+    for_each_page(&service, &cli_context(), |page| {
+        documents.extend(page);
+        Ok(())
+    })
+    .await
+    .context(Transfer)?;
+
+    let tera = templates(export.templates.as_deref())?;
+    let site = Site {
+        title: export.title.clone(),
+        base_url: export.base_url.trim_end_matches('/').to_string(),
+    };
+    let output = &export.output;
+    let tag_slugs = tag_slugs(documents.iter().flat_map(|document| &document.tags));
+    let pages = documents
+        .iter()
+        .map(|document| Page::new(document, &tag_slugs))
+        .collect::<Vec<_>>();
+
+    for page in &pages {
+        let mut context = tera::Context::new();
+        context.insert("site", &site);
+        context.insert("document", page);
+        render(&tera, "document.html", &context, &output.join(&page.path))?;
+    }
+
+    let genres = group_by(&pages, |page| {
+        vec![(page.genre.clone(), page.genre_slug.clone())]
+    });
+    let tags = group_by(&pages, |page| {
+        page.tags
+            .iter()
+            .map(|tag| (tag.name.clone(), tag.slug.clone()))
+            .collect()
+    });
+    for (directory, groups, label) in [("genres", &genres, "Genre"), ("tags", &tags, "Tag")] {
+        for (link, pages) in groups.values() {
+            let mut context = tera::Context::new();
+            context.insert("site", &site);
+            context.insert("heading", &format!("{}: {}", label, link.name));
+            context.insert("documents", pages);
+            let path = output.join(directory).join(format!("{}.html", link.slug));
+            render(&tera, "list.html", &context, &path)?;
+        }
+    }
+
+    // Documents are listed from the most recent.
+    let mut context = tera::Context::new();
+    context.insert("site", &site);
+    context.insert("genres", &links(&genres));
+    context.insert("tags", &links(&tags));
+    context.insert(
+        "documents",
+        &pages.iter().take(RECENT_DOCUMENTS).collect::<Vec<_>>(),
+    );
+    render(&tera, "index.html", &context, &output.join("index.html"))?;
+
+    write(
+        &output.join("sitemap.xml"),
+        sitemap(&site, &documents).as_bytes(),
+    )?;
+
+    let index = documents
+        .iter()
+        .map(|document| SearchEntry {
+            id: document.id,
+            title: &document.title,
+            outline: &document.outline,
+            tags: &document.tags,
+            genre: document.genre.as_str(),
+            url: format!("{}/documents/{}.html", site.base_url, document.id),
+        })
+        .collect::<Vec<_>>();
+    let index = serde_json::to_vec(&index).context(Json)?;
+    write(&output.join("search-index.json"), &index)?;
+
+    eprintln!(
+        "Exported {} documents to {}",
+        documents.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn templates(directory: Option<&Path>) -> Result<Tera, Error> {
+    match directory {
+        Some(directory) => {
+            Tera::new(&format!("{}/**/*.html", directory.display())).context(Template)
+        }
+        None => {
+            let mut tera = Tera::default();
+            tera.add_raw_templates(TEMPLATES.to_vec())
+                .context(Template)?;
+            Ok(tera)
+        }
+    }
+}
+
+fn render(tera: &Tera, template: &str, context: &tera::Context, path: &Path) -> Result<(), Error> {
+    let page = tera.render(template, context).context(Template)?;
+    write(path, page.as_bytes())
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context(Io { path: parent })?;
+    }
+    std::fs::write(path, contents).context(Io { path })
+}
+
+// Groups the pages by the keys returned by `f`, as (name, slug) pairs. Groups are
+// indexed by slug, so that names which only differ by their case end up together.
+fn group_by<'a, F>(pages: &'a [Page], f: F) -> BTreeMap<String, (Link, Vec<&'a Page>)>
+where
+    F: Fn(&Page) -> Vec<(String, String)>,
+{
+    let mut groups: BTreeMap<String, (Link, Vec<&Page>)> = BTreeMap::new();
+    for page in pages {
+        for (name, slug) in f(page) {
+            let (link, pages) = groups.entry(slug.clone()).or_insert_with(|| {
+                let link = Link {
+                    name,
+                    slug,
+                    count: 0,
+                };
+                (link, Vec::new())
+            });
+            link.count += 1;
+            pages.push(page);
+        }
+    }
+    groups
+}
+
+fn links(groups: &BTreeMap<String, (Link, Vec<&Page>)>) -> Vec<Link> {
+    groups.values().map(|(link, _)| link.clone()).collect()
+}
+
+fn sitemap(site: &Site, documents: &[Document]) -> String {
+    let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    sitemap.push_str(&format!(
+        "  <url><loc>{}/index.html</loc></url>\n",
+        xml::escape(&site.base_url)
+    ));
+    for document in documents {
+        sitemap.push_str(&format!(
+            "  <url><loc>{}/documents/{}.html</loc><lastmod>{}</lastmod></url>\n",
+            xml::escape(&site.base_url),
+            document.id,
+            document.updated_at.format("%Y-%m-%d")
+        ));
+    }
+    sitemap.push_str("</urlset>\n");
+    sitemap
+}
+
+// Turns a genre or a tag into a file name: 'to be decided' becomes 'to-be-decided'.
+fn slugify(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// The slugs of the tags, indexed by their lowercase name, since tags which only differ
+// by their case share their page. When tags have the same slug, eg. 'C++', 'C#' and
+// 'c', only the one spelled like the slug keeps it, the others are suffixed with a hash
+// of their name, which does not depend on the other tags. Tags made only of symbols
+// are named after the hash.
+fn tag_slugs<'a>(tags: impl Iterator<Item = &'a String>) -> HashMap<String, String> {
+    let mut names_by_slug: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for tag in tags {
+        names_by_slug
+            .entry(slugify(tag))
+            .or_default()
+            .insert(tag.to_lowercase());
+    }
+    let mut slugs = HashMap::new();
+    for (slug, names) in names_by_slug {
+        let unique = names.len() == 1 && !slug.is_empty();
+        for name in names {
+            let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+            let disambiguated = if unique || name == slug {
+                slug.clone()
+            } else if slug.is_empty() {
+                hash[..8].to_string()
+            } else {
+                format!("{}-{}", slug, &hash[..8])
+            };
+            slugs.insert(name, disambiguated);
+        }
+    }
+    slugs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use docstore_domain::model::document::Genre;
+
+    #[test]
+    fn should_render_document_page_with_its_html() {
+        let document = Document {
+            id: Uuid::new_v4(),
+            title: String::from("Getting <Started>"),
+            outline: String::from("abstract"),
+            content: String::from("# Install"),
+            html: String::from("<h1>Install</h1>"),
+            tags: vec![String::from("C++")],
+            genre: Genre::Tbd,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+            updated_by: None,
+        };
+        let site = Site {
+            title: String::from("Docs"),
+            base_url: String::from("https://docs.example.com"),
+        };
+        let mut context = tera::Context::new();
+        context.insert("site", &site);
+        let tag_slugs = tag_slugs(document.tags.iter());
+        context.insert("document", &Page::new(&document, &tag_slugs));
+        let page = templates(None)
+            .unwrap()
+            .render("document.html", &context)
+            .unwrap();
+        assert!(page.contains("<h1>Install</h1>"));
+        assert!(page.contains("Getting &lt;Started&gt;"));
+        assert!(page.contains("https://docs.example.com/genres/to-be-decided.html"));
+        assert!(page.contains("https://docs.example.com/tags/c.html"));
+    }
+
+    #[test]
+    fn should_give_distinct_slugs_to_colliding_tags() {
+        let tags = ["C++", "C#", "c", "C", "+++", "Rust"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect::<Vec<_>>();
+        let slugs = tag_slugs(tags.iter());
+        assert_eq!(slugs["c"], "c");
+        assert_eq!(slugs["rust"], "rust");
+        assert!(slugs["c++"].starts_with("c-"));
+        assert!(slugs["c#"].starts_with("c-"));
+        assert_ne!(slugs["c++"], slugs["c#"]);
+        assert_eq!(slugs["+++"].len(), 8);
+        assert_eq!(slugs.len(), 5);
+
+        // The slug of a tag does not depend on the other tags.
+        let alone = tag_slugs([String::from("C#"), String::from("c")].iter());
+        assert_eq!(alone["c#"], slugs["c#"]);
+    }
+}
@@ -0,0 +1,100 @@
+use http::{HeaderMap, Method};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::sdk::propagation::TraceContextPropagator;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::trace::TraceError;
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use std::time::Duration;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::settings::Otlp;
+
+/// Installs the W3C Trace Context propagator, and the OTLP exporter when it is
+/// configured. The returned tracer feeds the `tracing_opentelemetry` layer.
+pub fn tracer(config: Option<&Otlp>) -> Result<Option<trace::Tracer>, TraceError> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    config
+        .map(|config| {
+            opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(config.endpoint.as_str())
+                        .with_timeout(Duration::from_millis(config.timeout)),
+                )
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", config.service.clone()),
+                ])))
+                .install_batch(opentelemetry::runtime::Tokio)
+        })
+        .transpose()
+}
+
+/// Exports the spans which are still buffered.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// The trace context sent by the caller in 'traceparent' and 'tracestate'.
+fn parent_context(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// The root span of a request, child of the caller's span when the request carries a
+/// 'traceparent' header. The request id is recorded once the context of the request
+/// is built (see `ContextBuilder::build`).
+pub fn request_span(method: &Method, path: &str, headers: &HeaderMap) -> tracing::Span {
+    let span = tracing::info_span!(
+        "request",
+        otel.name = %format!("HTTP {}", method),
+        otel.kind = "server",
+        http.method = %method,
+        http.target = %path,
+        request_id = tracing::field::Empty,
+    );
+    span.set_parent(parent_context(headers));
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn should_extract_the_parent_from_traceparent() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let context = parent_context(&headers);
+        let span = context.span();
+        let parent = span.span_context();
+        assert!(parent.is_remote());
+        assert_eq!(
+            parent.trace_id().to_u128(),
+            0x4bf92f3577b34da6a3ce929d0e0e4736
+        );
+
+        let context = parent_context(&HeaderMap::new());
+        assert!(!context.span().span_context().is_valid());
+    }
+}
@@ -0,0 +1,299 @@
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use rustls_pemfile::Item;
+use snafu::{ResultExt, Snafu};
+use std::convert::Infallible;
+use std::fs::File;
+use std::future::Future;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use warp::hyper::server::accept;
+use warp::hyper::service::{make_service_fn, service_fn, Service};
+use warp::hyper::{Body, Request, Response, Server};
+use warp::Filter;
+
+use super::settings::Tls;
+
+// Maximum time given to a client to complete the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Pause after a failed accept: the usual causes, like running out of file
+// descriptors, do not go away at once, and retrying right away would spin.
+const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Could not read {}: {}", path.display(), source))]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("No certificate found in {}", path.display()))]
+    NoCertificate { path: PathBuf },
+
+    #[snafu(display("No private key found in {}", path.display()))]
+    NoPrivateKey { path: PathBuf },
+
+    #[snafu(display("Invalid TLS configuration: {}", source))]
+    Configuration { source: rustls::Error },
+
+    #[snafu(display("Could not listen on {}: {}", addr, source))]
+    Listen {
+        addr: SocketAddr,
+        source: std::io::Error,
+    },
+}
+
+fn certificates(path: &Path) -> Result<Vec<Certificate>, Error> {
+    let file = File::open(path).context(Read { path })?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file)).context(Read { path })?;
+    if certificates.is_empty() {
+        return NoCertificate { path }.fail();
+    }
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+fn private_key(path: &Path) -> Result<PrivateKey, Error> {
+    let file = File::open(path).context(Read { path })?;
+    let mut reader = BufReader::new(file);
+    loop {
+        match rustls_pemfile::read_one(&mut reader).context(Read { path })? {
+            Some(Item::PKCS8Key(key)) | Some(Item::RSAKey(key)) | Some(Item::ECKey(key)) => {
+                return Ok(PrivateKey(key))
+            }
+            Some(_) => continue,
+            None => return NoPrivateKey { path }.fail(),
+        }
+    }
+}
+
+/// Builds the TLS configuration from the files. Clients must present a certificate
+/// signed by one of the authorities of `client_ca_path`, when it is given.
+pub fn server_config(tls: &Tls) -> Result<ServerConfig, Error> {
+    let certificates = certificates(&tls.cert_path)?;
+    let key = private_key(&tls.key_path)?;
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &tls.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            let (added, _) = roots.add_parsable_certificates(
+                &certificates(path)?
+                    .into_iter()
+                    .map(|certificate| certificate.0)
+                    .collect::<Vec<_>>(),
+            );
+            if added == 0 {
+                return NoCertificate { path }.fail();
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots))
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certificates, key)
+        .context(Configuration)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// The TLS configuration used by the new connections. It is replaced when the
+/// certificates are reloaded, the connections in progress keep theirs.
+#[derive(Clone)]
+pub struct Acceptor {
+    config: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl Acceptor {
+    pub fn new(tls: &Tls) -> Result<Self, Error> {
+        Ok(Acceptor {
+            config: Arc::new(RwLock::new(Arc::new(server_config(tls)?))),
+        })
+    }
+
+    fn reload(&self, tls: &Tls) -> Result<(), Error> {
+        let config = server_config(tls)?;
+        *self.config.write().expect("TLS configuration lock") = Arc::new(config);
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.config.read().expect("TLS configuration lock").clone())
+    }
+}
+
+// The modification times of the files, to detect their changes.
+fn modified(tls: &Tls) -> Vec<Option<SystemTime>> {
+    std::iter::once(&tls.cert_path)
+        .chain(std::iter::once(&tls.key_path))
+        .chain(tls.client_ca_path.as_ref())
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Reloads the certificates on SIGHUP, or when one of the files changes. When the new
+/// files are invalid, eg. because they are being written, the previous ones are kept.
+pub async fn watch(acceptor: Acceptor, tls: Tls) {
+    let mut last = modified(&tls);
+    let mut interval = tokio::time::interval(Duration::from_millis(tls.reload_interval));
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .expect("SIGHUP handler installation");
+    loop {
+        #[cfg(unix)]
+        let forced = tokio::select! {
+            _ = interval.tick() => false,
+            _ = hangup.recv() => true,
+        };
+        #[cfg(not(unix))]
+        let forced = {
+            interval.tick().await;
+            false
+        };
+        let current = modified(&tls);
+        if !forced && current == last {
+            continue;
+        }
+        last = current;
+        match acceptor.reload(&tls) {
+            Ok(()) => tracing::info!("Reloaded the TLS certificates"),
+            Err(err) => tracing::error!(
+                "Could not reload the TLS certificates, keeping the previous ones: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Accepts the connections on `addr`, and yields them once their handshake succeeds.
+/// Handshakes run concurrently, and the failed ones are only logged, since an error
+/// in the stream would stop the server.
+pub async fn incoming(
+    addr: SocketAddr,
+    acceptor: Acceptor,
+) -> Result<impl Stream<Item = Result<TlsStream<TcpStream>, std::io::Error>>, Error> {
+    let listener = TcpListener::bind(addr).await.context(Listen { addr })?;
+    let (sender, receiver) = mpsc::channel(32);
+    tokio::spawn(async move {
+        loop {
+            // The server drops the stream when it shuts down.
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        tracing::warn!("Could not accept a connection: {}", err);
+                        tokio::time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                },
+                () = sender.closed() => break,
+            };
+            let acceptor = acceptor.acceptor();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = sender.send(stream).await;
+                    }
+                    Ok(Err(err)) => tracing::debug!("TLS handshake with {} failed: {}", peer, err),
+                    Err(_) => tracing::debug!("TLS handshake with {} timed out", peer),
+                }
+            });
+        }
+    });
+    Ok(ReceiverStream::new(receiver).map(Ok))
+}
+
+/// The address of the client of a request received over TLS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peer(pub SocketAddr);
+
+/// Serves the connections of `incoming` until `shutdown` resolves. Unlike
+/// `warp::serve(..).serve_incoming(..)`, which loses it, the address of the client
+/// is given to the requests, for `remote`.
+pub async fn serve<S>(
+    service: S,
+    incoming: impl Stream<Item = Result<TlsStream<TcpStream>, std::io::Error>> + Send + 'static,
+    shutdown: impl Future<Output = ()>,
+) where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let make_service = make_service_fn(move |stream: &TlsStream<TcpStream>| {
+        let peer = stream.get_ref().0.peer_addr().ok().map(Peer);
+        let service = service.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |mut request: Request<Body>| {
+                if let Some(peer) = peer {
+                    request.extensions_mut().insert(peer);
+                }
+                service.clone().call(request)
+            }))
+        }
+    });
+    let server = Server::builder(accept::from_stream(incoming))
+        .serve(make_service)
+        .with_graceful_shutdown(shutdown);
+    if let Err(err) = server.await {
+        tracing::error!("HTTP server error: {}", err);
+    }
+}
+
+/// The address of the client, whether the request is received over TLS or not.
+pub fn remote() -> impl Filter<Extract = (Option<SocketAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote().and(warp::ext::optional::<Peer>()).map(
+        |remote: Option<SocketAddr>, peer: Option<Peer>| {
+            remote.or_else(|| peer.map(|Peer(addr)| addr))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_give_the_address_of_tls_clients() {
+        let addr = SocketAddr::from(([192, 0, 2, 1], 443));
+        let remote_addr = warp::test::request()
+            .extension(Peer(addr))
+            .filter(&remote())
+            .await
+            .unwrap();
+        assert_eq!(remote_addr, Some(addr));
+        let remote_addr = warp::test::request().filter(&remote()).await.unwrap();
+        assert_eq!(remote_addr, None);
+    }
+
+    #[test]
+    fn should_reject_files_without_certificate() {
+        let name = format!("docstore-tls-{}.pem", uuid::Uuid::new_v4());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "not a certificate").unwrap();
+        let tls = Tls {
+            cert_path: path.clone(),
+            key_path: path.clone(),
+            client_ca_path: None,
+            reload_interval: 1000,
+        };
+        let err = server_config(&tls).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(err, Error::NoCertificate { .. }), "{}", err);
+    }
+}
@@ -0,0 +1,595 @@
+use chrono::{DateTime, Utc};
+use docstore_adapter_2ry_pg as postgresql;
+use docstore_domain::model::authorization::{Permission, Principal, RolePolicy};
+use docstore_domain::model::context::RequestContext;
+use docstore_domain::model::document::{
+    ConflictPolicy, Document, DocumentKey, ExportDocumentsRequest, Genre, ImportDocumentsRequest,
+    ImportSummary,
+};
+use docstore_domain::model::error::Error as ModelError;
+use docstore_domain::ports::primary::storage::DocumentStorage;
+use docstore_domain::service::document::DocumentService;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::settings::{
+    ArchiveFormat, Error as SettingsError, ExportOpts, ImportOpts, Opts, Settings,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Could not generate settings: {}", source))]
+    SettingsProcessing { source: SettingsError },
+
+    #[snafu(display("Store Error: {}", source))]
+    Store { source: postgresql::Error },
+
+    #[snafu(display("Model Error: {}", source))]
+    Model { source: ModelError },
+
+    #[snafu(display("IO Error with {}: {}", path.display(), source))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Invalid document at line {}: {}", line, source))]
+    InvalidJson {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Invalid document {}: {}", path, msg))]
+    InvalidMarkdown { path: String, msg: String },
+
+    #[snafu(display("Document {} appears more than once", id))]
+    DuplicateDocument { id: Uuid },
+}
+
+// Number of documents read from the storage at once during an export.
+const PAGE_SIZE: u32 = 100;
+
+// Number of documents sent to the storage at once during an import.
+const BATCH_SIZE: usize = 500;
+
+// The command line is trusted, so it acts with every permission.
+pub(crate) fn cli_context() -> RequestContext {
+    RequestContext::new(Principal {
+        subject: Some(String::from("docstore-cli")),
+        roles: Vec::new(),
+        scopes: Some(vec![Permission::Read, Permission::Write, Permission::Admin]),
+    })
+}
+
+pub(crate) async fn document_service(opts: &Opts) -> Result<DocumentService, Error> {
+    let settings = Settings::new(opts).context(SettingsProcessing)?;
+    let store = postgresql::PostgresqlStorage::new(&settings.postgresql)
+        .await
+        .context(Store)?;
+    let policy = RolePolicy::new(settings.authorization.policy.clone());
+    Ok(
+        DocumentService::new(Box::new(store), Box::new(policy))
+            .with_validation(settings.validation),
+    )
+}
+
+/// Reads every document through the storage port, and calls `f` with each page.
+/// Returns the number of documents.
+pub(crate) async fn for_each_page<F>(
+    service: &DocumentService,
+    context: &RequestContext,
+    mut f: F,
+) -> Result<usize, Error>
+where
+    F: FnMut(Vec<Document>) -> Result<(), Error>,
+{
+    let mut after = None;
+    let mut count = 0;
+    loop {
+        let request = ExportDocumentsRequest {
+            after,
+            limit: PAGE_SIZE,
+        };
+        let documents = service
+            .export_documents(context, &request)
+            .await
+            .context(Model)?;
+        let last = documents.len() < PAGE_SIZE as usize;
+        after = documents.last().map(DocumentKey::from);
+        count += documents.len();
+        f(documents)?;
+        if last {
+            return Ok(count);
+        }
+    }
+}
+
+/// Writes every document to the file, page by page.
+pub async fn export(opts: &Opts, export: &ExportOpts) -> Result<(), Error> {
+    let service = document_service(opts).await?;
+    let context = cli_context();
+    let path = &export.file;
+    let mut writer = ArchiveWriter::new(export.format, create(path).context(Io { path })?);
+
+    let count = for_each_page(&service, &context, |documents| {
+        for document in documents {
+            writer.write(&document).context(Io { path })?;
+        }
+        Ok(())
+    })
+    .await?;
+    writer.finish().context(Io { path })?;
+
+    eprintln!("Exported {} documents to {}", count, path.display());
+    Ok(())
+}
+
+/// Restores the documents of the file.
+pub async fn import(opts: &Opts, import: &ImportOpts) -> Result<(), Error> {
+    let service = document_service(opts).await?;
+    let context = cli_context();
+    let path = &import.file;
+    let input = open(path).context(Io { path })?;
+
+    let documents: Box<dyn Iterator<Item = Result<Document, Error>> + '_> = match import.format {
+        ArchiveFormat::Ndjson => Box::new(read_ndjson(input, path)),
+        // The Markdown and the html of a document are in different entries, so the whole
+        // archive is read before the documents are imported.
+        ArchiveFormat::Markdown => {
+            Box::new(read_markdown_archive(input, path)?.into_iter().map(Ok))
+        }
+    };
+    let summary = import_documents(&service, &context, documents, import.on_conflict).await?;
+
+    eprintln!(
+        "Imported {}: {} created, {} overwritten, {} skipped",
+        path.display(),
+        summary.created,
+        summary.overwritten,
+        summary.skipped
+    );
+    Ok(())
+}
+
+/// Imports the documents batch by batch, each batch in its own transaction, so an
+/// interrupted import can be run again. When the import must fail on a conflict,
+/// nothing must be written, so the documents are imported in a single transaction.
+async fn import_documents<I>(
+    service: &DocumentService,
+    context: &RequestContext,
+    documents: I,
+    on_conflict: ConflictPolicy,
+) -> Result<ImportSummary, Error>
+where
+    I: Iterator<Item = Result<Document, Error>>,
+{
+    let batch_size = match on_conflict {
+        ConflictPolicy::Fail => usize::MAX,
+        ConflictPolicy::Skip | ConflictPolicy::Overwrite => BATCH_SIZE,
+    };
+    let mut ids = HashSet::new();
+    let mut summary = ImportSummary::default();
+    let mut batch = Vec::new();
+    for document in documents {
+        let document = document?;
+        if !ids.insert(document.id) {
+            return Err(Error::DuplicateDocument { id: document.id });
+        }
+        batch.push(document);
+        if batch.len() == batch_size {
+            import_batch(service, context, &mut batch, on_conflict, &mut summary).await?;
+        }
+    }
+    import_batch(service, context, &mut batch, on_conflict, &mut summary).await?;
+    Ok(summary)
+}
+
+async fn import_batch(
+    service: &DocumentService,
+    context: &RequestContext,
+    batch: &mut Vec<Document>,
+    on_conflict: ConflictPolicy,
+    summary: &mut ImportSummary,
+) -> Result<(), Error> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let request = ImportDocumentsRequest {
+        documents: std::mem::take(batch),
+        on_conflict,
+    };
+    let imported = service
+        .import_documents(context, &request)
+        .await
+        .context(Model)?;
+    summary.created += imported.created;
+    summary.overwritten += imported.overwritten;
+    summary.skipped += imported.skipped;
+    Ok(())
+}
+
+// One document per line, blank lines are ignored.
+fn read_ndjson<'a>(
+    input: Box<dyn Read>,
+    path: &'a Path,
+) -> impl Iterator<Item = Result<Document, Error>> + 'a {
+    BufReader::new(input)
+        .lines()
+        .enumerate()
+        .filter_map(move |(index, line)| match line.context(Io { path }) {
+            Err(err) => Some(Err(err)),
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(serde_json::from_str(&line).context(InvalidJson { line: index + 1 })),
+        })
+}
+
+fn create(path: &Path) -> std::io::Result<Box<dyn Write>> {
+    if path == Path::new("-") {
+        Ok(Box::new(std::io::stdout()))
+    } else {
+        Ok(Box::new(std::fs::File::create(path)?))
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<Box<dyn Read>> {
+    if path == Path::new("-") {
+        Ok(Box::new(std::io::stdin()))
+    } else {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+}
+
+enum ArchiveWriter {
+    Ndjson(BufWriter<Box<dyn Write>>),
+    // Each document is stored as 'documents/<id>.md', with its html next to it in
+    // 'documents/<id>.html'.
+    Markdown(tar::Builder<GzEncoder<Box<dyn Write>>>),
+}
+
+impl ArchiveWriter {
+    fn new(format: ArchiveFormat, output: Box<dyn Write>) -> Self {
+        match format {
+            ArchiveFormat::Ndjson => ArchiveWriter::Ndjson(BufWriter::new(output)),
+            ArchiveFormat::Markdown => ArchiveWriter::Markdown(tar::Builder::new(GzEncoder::new(
+                output,
+                Compression::default(),
+            ))),
+        }
+    }
+
+    fn write(&mut self, document: &Document) -> std::io::Result<()> {
+        match self {
+            ArchiveWriter::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, document)?;
+                writer.write_all(b"\n")
+            }
+            ArchiveWriter::Markdown(builder) => {
+                let markdown = to_markdown(document)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+                let mtime = document.updated_at;
+                append(
+                    builder,
+                    format!("documents/{}.md", document.id),
+                    markdown.as_bytes(),
+                    mtime,
+                )?;
+                append(
+                    builder,
+                    format!("documents/{}.html", document.id),
+                    document.html.as_bytes(),
+                    mtime,
+                )
+            }
+        }
+    }
+
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            ArchiveWriter::Ndjson(mut writer) => writer.flush(),
+            ArchiveWriter::Markdown(builder) => builder.into_inner()?.finish()?.flush(),
+        }
+    }
+}
+
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: String,
+    data: &[u8],
+    mtime: DateTime<Utc>,
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime.timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data)
+}
+
+fn read_markdown_archive(input: Box<dyn Read>, path: &Path) -> Result<Vec<Document>, Error> {
+    let mut archive = tar::Archive::new(GzDecoder::new(input));
+    let mut markdowns = BTreeMap::new();
+    let mut htmls = BTreeMap::new();
+    for entry in archive.entries().context(Io { path })? {
+        let mut entry = entry.context(Io { path })?;
+        let name = entry.path().context(Io { path })?.to_path_buf();
+        let (stem, extension) = match (name.file_stem(), name.extension()) {
+            (Some(stem), Some(extension)) => (
+                stem.to_string_lossy().to_string(),
+                extension.to_string_lossy().to_string(),
+            ),
+            _ => continue,
+        };
+        let mut data = String::new();
+        entry.read_to_string(&mut data).context(Io { path })?;
+        match extension.as_str() {
+            "md" => {
+                markdowns.insert(stem, (name.display().to_string(), data));
+            }
+            "html" => {
+                htmls.insert(stem, data);
+            }
+            _ => {}
+        }
+    }
+
+    markdowns
+        .into_iter()
+        .map(|(stem, (name, markdown))| {
+            let html = htmls.remove(&stem).unwrap_or_default();
+            from_markdown(&markdown, html).map_err(|msg| Error::InvalidMarkdown { path: name, msg })
+        })
+        .collect()
+}
+
+// Everything but the content and the html, which are stored separately.
+#[derive(Serialize, Deserialize)]
+struct FrontMatter {
+    id: Uuid,
+    title: String,
+    #[serde(rename = "abstract")]
+    outline: String,
+    tags: Vec<String>,
+    genre: Genre,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    created_by: Option<String>,
+    updated_by: Option<String>,
+}
+
+// A Markdown file, with the document's metadata in a YAML front matter.
+fn to_markdown(document: &Document) -> Result<String, serde_yaml::Error> {
+    let front_matter = FrontMatter {
+        id: document.id,
+        title: document.title.clone(),
+        outline: document.outline.clone(),
+        tags: document.tags.clone(),
+        genre: document.genre.clone(),
+        created_at: document.created_at,
+        updated_at: document.updated_at,
+        created_by: document.created_by.clone(),
+        updated_by: document.updated_by.clone(),
+    };
+    let yaml = serde_yaml::to_string(&front_matter)?;
+    let yaml = yaml.trim_start_matches("---\n").trim_end();
+    Ok(format!("---\n{}\n---\n\n{}", yaml, document.content))
+}
+
+fn from_markdown(markdown: &str, html: String) -> Result<Document, String> {
+    let rest = markdown
+        .strip_prefix("---\n")
+        .ok_or_else(|| String::from("missing front matter"))?;
+    let (yaml, content) = rest
+        .split_once("\n---\n")
+        .ok_or_else(|| String::from("unterminated front matter"))?;
+    let front_matter: FrontMatter = serde_yaml::from_str(yaml).map_err(|err| err.to_string())?;
+    Ok(Document {
+        id: front_matter.id,
+        title: front_matter.title,
+        outline: front_matter.outline,
+        content: content.strip_prefix('\n').unwrap_or(content).to_string(),
+        html,
+        tags: front_matter.tags,
+        genre: front_matter.genre,
+        created_at: front_matter.created_at,
+        updated_at: front_matter.updated_at,
+        created_by: front_matter.created_by,
+        updated_by: front_matter.updated_by,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use docstore_domain::ports::secondary::authorization::MockAuthorizationPolicy;
+    use docstore_domain::ports::secondary::storage::MockDocumentStorage;
+
+    fn document(id: u128) -> Document {
+        Document {
+            id: Uuid::from_u128(id),
+            title: String::from("Getting Started"),
+            outline: String::from("How to: install the docstore"),
+            content: String::from("# Install\n\n---\n\nRun `make`.\n"),
+            html: String::from("<h1>Install</h1>"),
+            tags: vec![String::from("rust")],
+            genre: Genre::Tutorial,
+            created_at: Utc.ymd(2022, 4, 5).and_hms(10, 0, 0),
+            updated_at: Utc.ymd(2022, 4, 6).and_hms(10, 0, 0),
+            created_by: Some(String::from("alice")),
+            updated_by: None,
+        }
+    }
+
+    fn documents(count: usize) -> impl Iterator<Item = Result<Document, Error>> {
+        (0..count).map(|id| Ok(document(id as u128)))
+    }
+
+    // Every request reaches the storage.
+    fn service(storage: MockDocumentStorage) -> DocumentService {
+        let mut policy = MockAuthorizationPolicy::new();
+        policy.expect_authorize().returning(|_, _| Ok(()));
+        DocumentService::new(Box::new(storage), Box::new(policy))
+    }
+
+    fn created(documents: &[Document]) -> Result<ImportSummary, ModelError> {
+        Ok(ImportSummary {
+            created: documents.len(),
+            ..ImportSummary::default()
+        })
+    }
+
+    #[test]
+    fn should_restore_document_from_markdown() {
+        let document = document(1);
+        let markdown = to_markdown(&document).unwrap();
+        let restored = from_markdown(&markdown, document.html.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&document).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_import_documents_from_ndjson() {
+        let mut archive = Vec::new();
+        for document in [document(1), document(2)] {
+            serde_json::to_writer(&mut archive, &document).unwrap();
+            archive.extend_from_slice(b"\n\n");
+        }
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_import_documents()
+            .withf(|_, documents, _| {
+                documents
+                    .iter()
+                    .map(|document| document.id)
+                    .collect::<Vec<_>>()
+                    == vec![Uuid::from_u128(1), Uuid::from_u128(2)]
+            })
+            .times(1)
+            .returning(|_, documents, _| created(documents));
+
+        let path = Path::new("-");
+        let documents = read_ndjson(Box::new(std::io::Cursor::new(archive)), path);
+        let summary = import_documents(
+            &service(storage),
+            &cli_context(),
+            documents,
+            ConflictPolicy::Skip,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.created, 2);
+    }
+
+    #[test]
+    fn should_report_the_line_of_an_invalid_document() {
+        let archive = format!(
+            "{}\nnot a document\n",
+            serde_json::to_string(&document(1)).unwrap()
+        );
+        let path = Path::new("-");
+        let results =
+            read_ndjson(Box::new(std::io::Cursor::new(archive)), path).collect::<Vec<_>>();
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(Error::InvalidJson { line: 2, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_import_in_a_single_transaction_when_failing_on_conflict() {
+        let mut storage = MockDocumentStorage::new();
+        storage
+            .expect_import_documents()
+            .withf(|_, documents, on_conflict| {
+                documents.len() == BATCH_SIZE + 1 && *on_conflict == ConflictPolicy::Fail
+            })
+            .times(1)
+            .returning(|_, documents, _| {
+                Err(ModelError::Conflict {
+                    ids: vec![documents[BATCH_SIZE].id],
+                })
+            });
+
+        let result = import_documents(
+            &service(storage),
+            &cli_context(),
+            documents(BATCH_SIZE + 1),
+            ConflictPolicy::Fail,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::Model {
+                source: ModelError::Conflict { .. }
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_import_batch_by_batch_when_skipping_or_overwriting() {
+        for policy in [ConflictPolicy::Skip, ConflictPolicy::Overwrite] {
+            let mut storage = MockDocumentStorage::new();
+            storage
+                .expect_import_documents()
+                .withf(move |_, documents, on_conflict| {
+                    documents.len() <= BATCH_SIZE && *on_conflict == policy
+                })
+                .times(2)
+                .returning(move |_, documents, _| {
+                    // The first document of each batch already exists.
+                    let mut summary = ImportSummary {
+                        created: documents.len() - 1,
+                        ..ImportSummary::default()
+                    };
+                    if policy == ConflictPolicy::Overwrite {
+                        summary.overwritten = 1;
+                    } else {
+                        summary.skipped = 1;
+                    }
+                    Ok(summary)
+                });
+
+            let summary = import_documents(
+                &service(storage),
+                &cli_context(),
+                documents(BATCH_SIZE + 1),
+                policy,
+            )
+            .await
+            .unwrap();
+            let existing = if policy == ConflictPolicy::Overwrite {
+                summary.overwritten
+            } else {
+                summary.skipped
+            };
+            assert_eq!(summary.created, BATCH_SIZE - 1);
+            assert_eq!(existing, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_a_document_which_appears_twice() {
+        let mut storage = MockDocumentStorage::new();
+        storage.expect_import_documents().never();
+
+        let documents = vec![Ok(document(1)), Ok(document(1))];
+        let result = import_documents(
+            &service(storage),
+            &cli_context(),
+            documents.into_iter(),
+            ConflictPolicy::Overwrite,
+        )
+        .await;
+        assert!(matches!(result, Err(Error::DuplicateDocument { .. })));
+    }
+}